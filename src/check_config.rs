@@ -0,0 +1,64 @@
+use tts_core::structs::{Config, Result};
+
+use crate::startup::{build_http, build_reqwest, get_webhooks, log_filter, tts_service_configs};
+
+/// Tests the connections the config sets up, for the `check-config` command.
+///
+/// The config has been parsed and the database connected to before this is called.
+pub async fn check_config(config: Config) -> Result<()> {
+    let mut problems = 0;
+
+    let token = config.main.token.as_deref();
+    if token.is_none() {
+        println!("`token` is not set in [Main]");
+        problems += 1;
+    }
+
+    if config.main.tts_service.is_none()
+        && config.tts_services.is_empty()
+        && config.local_tts.is_none()
+        && config.openai_tts.is_none()
+    {
+        println!(
+            "One of `tts_service`, `[[TTS-Service]]`, `[Local-TTS]` or `[OpenAI-TTS]` must be set"
+        );
+        problems += 1;
+    }
+
+    if let Err(err) = log_filter(&config.main, &config.logging) {
+        println!("{err}");
+        problems += 1;
+    }
+
+    let reqwest = build_reqwest(&config.main)?;
+    for service in tts_service_configs(&config.main, config.tts_services) {
+        let url = service.url;
+        match reqwest.get(url.clone()).send().await {
+            Ok(resp) if !resp.status().is_server_error() => {
+                println!("Connected to tts-service at {url}");
+            }
+            Ok(resp) => {
+                println!("tts-service at {url} returned {}", resp.status());
+                problems += 1;
+            }
+            Err(err) => {
+                println!("Failed to connect to tts-service at {url}: {err}");
+                problems += 1;
+            }
+        }
+    }
+
+    // Fetching webhooks with their token does not need the bot token to be valid.
+    let http = build_http(&config.main, token.unwrap_or_default());
+    if let Err(err) = get_webhooks(&http, config.webhooks).await {
+        println!("Failed to fetch webhooks: {err:?}");
+        problems += 1;
+    }
+
+    if problems != 0 {
+        anyhow::bail!("Found {problems} problems with the config");
+    }
+
+    println!("The config is valid");
+    Ok(())
+}
@@ -0,0 +1,331 @@
+use std::{
+    future::Future,
+    sync::{atomic::Ordering, Arc},
+    time::{Duration, Instant},
+};
+
+use poise::serenity_prelude as serenity;
+
+use tokio::{
+    io::{AsyncBufReadExt as _, AsyncReadExt as _, AsyncWriteExt as _, BufReader},
+    net::{TcpListener, TcpStream},
+};
+
+use tts_core::{
+    backends::Endpoints,
+    database,
+    metrics::METRICS,
+    structs::{Data, Result},
+    tasks::TASKS,
+};
+
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Response {
+    pub fn text(status: u16, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: body.into(),
+        }
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            503 => "Service Unavailable",
+            _ => "Internal Server Error",
+        }
+    }
+}
+
+/// The longest request or header line accepted, so a client cannot fill up memory.
+const MAX_LINE_LEN: u64 = 8 * 1024;
+/// The most headers accepted in a request.
+const MAX_HEADERS: usize = 64;
+
+/// Reads a line of the request, returning how many bytes were read.
+async fn read_line(reader: &mut BufReader<&mut TcpStream>, line: &mut String) -> Result<usize> {
+    let read = reader.take(MAX_LINE_LEN).read_line(line).await?;
+    if read as u64 == MAX_LINE_LEN && !line.ends_with('\n') {
+        anyhow::bail!("Request line is longer than {MAX_LINE_LEN} bytes");
+    }
+
+    Ok(read)
+}
+
+async fn handle_connection<F, Fut>(mut stream: TcpStream, handler: F) -> Result<()>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Response>,
+{
+    let mut reader = BufReader::new(&mut stream);
+
+    let mut request_line = String::new();
+    read_line(&mut reader, &mut request_line).await?;
+
+    // The headers are not used, but have to be read before responding.
+    for header_count in 0.. {
+        if header_count == MAX_HEADERS {
+            anyhow::bail!("Request has more than {MAX_HEADERS} headers");
+        }
+
+        let mut header = String::new();
+        if read_line(&mut reader, &mut header).await? == 0 || header.trim_end().is_empty() {
+            break;
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let response = match (parts.next(), parts.next()) {
+        (Some("GET"), Some(path)) => handler(path.to_owned()).await,
+        _ => Response::text(405, "Only GET requests are supported"),
+    };
+
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.reason(),
+        response.content_type,
+        response.body.len()
+    );
+
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(response.body.as_bytes()).await?;
+    Ok(())
+}
+
+/// A minimal HTTP/1.1 server, only for scraping by monitoring on a private network.
+///
+/// `listener` is bound by the caller, so a taken address fails startup instead of this task.
+/// `handler` is called with the path of each `GET` request.
+pub async fn serve<F, Fut>(listener: TcpListener, handler: F)
+where
+    F: Fn(String) -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = Response> + Send,
+{
+    loop {
+        let (stream, _) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(err) => {
+                tracing::warn!("Failed to accept HTTP connection: {err}");
+                continue;
+            }
+        };
+
+        let handler = handler.clone();
+        tokio::spawn(async move {
+            let timeout = Duration::from_secs(30);
+            match tokio::time::timeout(timeout, handle_connection(stream, handler)).await {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => tracing::debug!("Failed to respond to HTTP request: {err}"),
+                Err(_) => tracing::debug!("HTTP request timed out"),
+            }
+        });
+    }
+}
+
+async fn encode_metrics(
+    data: Arc<Data>,
+    cache: Arc<serenity::Cache>,
+    shard_manager: &serenity::ShardManager,
+) -> Result<String> {
+    METRICS.update_shards(&*shard_manager.runners.lock().await);
+    tokio::task::spawn_blocking(move || {
+        METRICS.update(&data, &cache);
+        METRICS.encode()
+    })
+    .await?
+}
+
+/// Serves Prometheus metrics at `/metrics`.
+pub async fn serve_metrics(
+    listener: TcpListener,
+    data: Arc<Data>,
+    cache: Arc<serenity::Cache>,
+    shard_manager: Arc<serenity::ShardManager>,
+) {
+    serve(listener, move |path| {
+        let (data, cache, shard_manager) = (data.clone(), cache.clone(), shard_manager.clone());
+        async move {
+            if path != "/metrics" {
+                return Response::text(404, "Not Found");
+            }
+
+            match encode_metrics(data, cache, &shard_manager).await {
+                Ok(body) => Response {
+                    status: 200,
+                    content_type: "text/plain; version=0.0.4; charset=utf-8",
+                    body,
+                },
+                Err(err) => {
+                    tracing::error!("Failed to encode metrics: {err:?}");
+                    Response::text(500, "Failed to encode metrics")
+                }
+            }
+        }
+    })
+    .await
+}
+
+/// How long each health check can take before it counts as failed.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct Check {
+    ok: bool,
+    detail: String,
+}
+
+impl Check {
+    fn new(ok: bool, detail: impl Into<String>) -> Self {
+        Self {
+            ok,
+            detail: detail.into(),
+        }
+    }
+}
+
+fn health_response(checks: Vec<(&'static str, Check)>) -> Response {
+    let ok = checks.iter().all(|(_, check)| check.ok);
+    let checks: serde_json::Map<_, _> = checks
+        .into_iter()
+        .map(|(name, check)| {
+            let check = serde_json::json!({"ok": check.ok, "detail": check.detail});
+            (String::from(name), check)
+        })
+        .collect();
+
+    let body = serde_json::json!({
+        "status": if ok { "ok" } else { "fail" },
+        "checks": checks,
+    });
+
+    Response {
+        status: if ok { 200 } else { 503 },
+        content_type: "application/json",
+        body: body.to_string(),
+    }
+}
+
+/// Checks that the runtime can still run new tasks.
+async fn check_runtime() -> Check {
+    let start = Instant::now();
+    match tokio::time::timeout(CHECK_TIMEOUT, tokio::spawn(async {})).await {
+        Ok(Ok(())) => Check::new(true, format!("Spawned a task in {:?}", start.elapsed())),
+        Ok(Err(err)) => Check::new(false, format!("Spawned task failed: {err}")),
+        Err(_) => Check::new(false, "Spawning a task timed out"),
+    }
+}
+
+async fn check_database(data: &Data) -> Check {
+    match tokio::time::timeout(CHECK_TIMEOUT, database::ping(&data.pool)).await {
+        Ok(Ok(())) => Check::new(true, "Reachable"),
+        Ok(Err(err)) => Check::new(false, format!("{err}")),
+        Err(_) => Check::new(false, "Timed out"),
+    }
+}
+
+async fn check_tts_service(tts_endpoints: &Endpoints) -> Check {
+    if tts_endpoints.is_empty() {
+        Check::new(true, "Not configured")
+    } else {
+        match tokio::time::timeout(CHECK_TIMEOUT, tts_endpoints.is_reachable()).await {
+            Ok(true) => Check::new(true, "Reachable"),
+            Ok(false) => Check::new(false, "No instance answered"),
+            Err(_) => Check::new(false, "Timed out"),
+        }
+    }
+}
+
+async fn check_shards(shard_manager: &serenity::ShardManager) -> Check {
+    let runners = shard_manager.runners.lock().await;
+    let connected = runners
+        .values()
+        .filter(|info| info.stage == serenity::ConnectionStage::Connected)
+        .count();
+
+    let detail = format!("{connected}/{} shards connected", runners.len());
+    Check::new(!runners.is_empty() && connected == runners.len(), detail)
+}
+
+/// Checks each background task, which fails if its last few runs have failed.
+///
+/// This is kept out of readiness, as an outage of a bot list should not restart the bot.
+fn task_checks() -> Vec<(&'static str, Check)> {
+    let describe = |time: Option<std::time::SystemTime>| match time.map(|time| time.elapsed()) {
+        Some(Ok(elapsed)) => format!("{}s ago", elapsed.as_secs()),
+        Some(Err(_)) | None => String::from("never"),
+    };
+
+    TASKS
+        .snapshot()
+        .into_iter()
+        .map(|(name, status)| {
+            let mut detail = format!(
+                "Last succeeded {}, {} failures in a row",
+                describe(status.last_success),
+                status.consecutive_failures
+            );
+
+            if status.stopped {
+                detail.push_str(", stopped");
+            }
+
+            (name, Check::new(!status.is_failing(), detail))
+        })
+        .collect()
+}
+
+async fn readiness(
+    data: &Data,
+    shard_manager: &serenity::ShardManager,
+    tts_endpoints: &Endpoints,
+) -> Response {
+    let (database, tts_service, shards) = tokio::join!(
+        check_database(data),
+        check_tts_service(tts_endpoints),
+        check_shards(shard_manager),
+    );
+
+    let started = if data.fully_started.load(Ordering::Relaxed) {
+        Check::new(true, "Started")
+    } else {
+        Check::new(false, "Still starting up")
+    };
+
+    health_response(vec![
+        ("database", database),
+        ("tts_service", tts_service),
+        ("shards", shards),
+        ("started", started),
+    ])
+}
+
+/// Serves liveness at `/health/live`, readiness at `/health/ready` and background task status
+/// at `/health/tasks`, as JSON.
+pub async fn serve_health(
+    listener: TcpListener,
+    data: Arc<Data>,
+    shard_manager: Arc<serenity::ShardManager>,
+    tts_endpoints: Arc<Endpoints>,
+) {
+    serve(listener, move |path| {
+        let (data, shard_manager) = (data.clone(), shard_manager.clone());
+        let tts_endpoints = tts_endpoints.clone();
+        async move {
+            match path.as_str() {
+                "/health/live" => health_response(vec![("runtime", check_runtime().await)]),
+                "/health/ready" => readiness(&data, &shard_manager, &tts_endpoints).await,
+                "/health/tasks" => health_response(task_checks()),
+                _ => Response::text(404, "Not Found"),
+            }
+        }
+    })
+    .await
+}
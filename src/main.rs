@@ -22,23 +22,35 @@
 )]
 
 use std::{
-    collections::BTreeMap,
-    sync::{atomic::AtomicBool, Arc},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize},
+        Arc,
+    },
     time::Duration,
 };
 
-use anyhow::Ok;
-use parking_lot::Mutex;
+use anyhow::{Context as _, Ok};
+use parking_lot::{Mutex, RwLock};
 
 use poise::serenity_prelude as serenity;
 use serenity::small_fixed_array::FixedString;
 
 use tts_core::{
-    analytics, create_db_handler, database,
-    structs::{Data, PollyVoice, RegexCache, Result, TTSMode},
+    analytics,
+    backends::{
+        Endpoints, HttpBackend, LocalBackend, ModeHealth, OpenAIBackend, RequestLimiter, TtsBackend,
+    },
+    create_db_handler, database, dynamic_modes, kill_switches,
+    structs::{Data, RegexCache, Result},
+    voice_lists::VoiceLists,
 };
+use tts_migrations::RunMode;
 use tts_tasks::Looper as _;
 
+mod check_config;
+mod http_server;
+mod reload;
+mod shutdown;
 mod startup;
 
 use startup::*;
@@ -55,25 +67,64 @@ fn main() -> Result<()> {
 }
 
 async fn main_(start_time: std::time::SystemTime) -> Result<()> {
+    let run_mode = RunMode::from_args()?;
+
     println!("Loading and performing migrations");
-    let (pool, config) = tts_migrations::load_db_and_conf().await?;
+    let (pool, config, config_toml) = tts_migrations::load_db_and_conf(run_mode).await?;
+    match run_mode {
+        RunMode::Normal => {}
+        RunMode::CheckConfig => return check_config::check_config(config).await,
+        RunMode::MigrateOnly | RunMode::DryRun => return Ok(()),
+    }
+
+    let log_filter = log_filter(&config.main, &config.logging)?;
 
     println!("Initialising Http client");
-    let reqwest = reqwest::Client::new();
-    let auth_key = config.main.tts_service_auth_key.as_deref();
-
-    let mut http_builder = serenity::HttpBuilder::new(config.main.token.as_deref().unwrap());
-    if let Some(proxy) = &config.main.proxy_url {
-        println!("Connecting via proxy");
-        http_builder = http_builder
-            .proxy(proxy.as_str())
-            .ratelimiter_disabled(true);
+    let reqwest = build_reqwest(&config.main)?;
+
+    let mut tts_backends: Vec<Box<dyn TtsBackend>> = Vec::new();
+    if let Some(local_tts) = config.local_tts {
+        println!("Using local TTS engine");
+        tts_backends.push(Box::new(LocalBackend::new(local_tts)?));
+    }
+
+    if let Some(openai_tts) = config.openai_tts {
+        println!("Using OpenAI compatible TTS");
+        tts_backends.push(Box::new(OpenAIBackend::new(reqwest.clone(), openai_tts)?));
+    }
+
+    let tts_services = tts_service_configs(&config.main, config.tts_services);
+    let auth_key = config.main.tts_service_auth_key.clone();
+    let tts_endpoints = Arc::new(Endpoints::new(reqwest.clone(), tts_services, auth_key));
+    if !tts_endpoints.is_empty() {
+        let endpoints = tts_endpoints.clone();
+        tts_backends.push(Box::new(HttpBackend::new(
+            reqwest.clone(),
+            endpoints,
+            config.main.tts_max_retries.unwrap_or(2),
+        )));
+    }
+
+    if tts_backends.is_empty() {
+        anyhow::bail!(
+            "One of `tts_service`, `[[TTS-Service]]` or `[Local-TTS]` must be set in the config"
+        );
     }
 
-    let http = Arc::new(http_builder.build());
+    println!("Registering dynamic TTS modes");
+    dynamic_modes::load(&pool, &tts_backends, config.tts_modes).await?;
+
+    let max_concurrency = config.main.tts_max_concurrency;
+    let request_limiter =
+        RequestLimiter::new(max_concurrency.unwrap_or(RequestLimiter::DEFAULT_MAX_CONCURRENCY));
+
+    let http = Arc::new(build_http(
+        &config.main,
+        config.main.token.as_deref().unwrap(),
+    ));
 
     println!("Performing big startup join");
-    let tts_service = || config.main.tts_service.clone();
+    let db_cache = &config.db_cache;
     let (
         webhooks,
         guilds_db,
@@ -81,24 +132,22 @@ async fn main_(start_time: std::time::SystemTime) -> Result<()> {
         user_voice_db,
         guild_voice_db,
         nickname_db,
-        gtts_voices,
-        espeak_voices,
-        gcloud_voices,
-        polly_voices,
-        translation_languages,
+        voice_lists,
+        mode_kill_switches,
         premium_user,
     ) = tokio::try_join!(
         get_webhooks(&http, config.webhooks),
-        create_db_handler!(pool.clone(), "guilds", "guild_id"),
-        create_db_handler!(pool.clone(), "userinfo", "user_id"),
-        create_db_handler!(pool.clone(), "user_voice", "user_id", "mode"),
-        create_db_handler!(pool.clone(), "guild_voice", "guild_id", "mode"),
-        create_db_handler!(pool.clone(), "nicknames", "guild_id", "user_id"),
-        fetch_voices(&reqwest, tts_service(), auth_key, TTSMode::gTTS),
-        fetch_voices(&reqwest, tts_service(), auth_key, TTSMode::eSpeak),
-        fetch_voices(&reqwest, tts_service(), auth_key, TTSMode::gCloud),
-        fetch_voices::<Vec<PollyVoice>>(&reqwest, tts_service(), auth_key, TTSMode::Polly),
-        fetch_translation_languages(&reqwest, tts_service(), auth_key),
+        create_db_handler!(pool.clone(), db_cache, "guilds", "guild_id"),
+        create_db_handler!(pool.clone(), db_cache, "userinfo", "user_id"),
+        create_db_handler!(pool.clone(), db_cache, "user_voice", "user_id", "mode"),
+        create_db_handler!(pool.clone(), db_cache, "guild_voice", "guild_id", "mode"),
+        create_db_handler!(pool.clone(), db_cache, "nicknames", "guild_id", "user_id"),
+        async {
+            let res = VoiceLists::fetch(&pool, &tts_backends).await?;
+            println!("Loaded voice lists");
+            Ok(res)
+        },
+        kill_switches::fetch_all(&pool),
         async {
             let res = serenity::UserId::new(802632257658683442)
                 .to_user(&http)
@@ -109,26 +158,39 @@ async fn main_(start_time: std::time::SystemTime) -> Result<()> {
         }
     )?;
 
-    println!("Setting up webhook logging");
-    tts_tasks::logging::WebhookLogger::init(
+    println!("Setting up logging");
+    let logger = tts_tasks::logging::WebhookLogger::init(
         http.clone(),
+        log_filter,
+        &config.logging,
         webhooks.logs.clone(),
         webhooks.errors.clone(),
-    );
+    )?;
 
     println!("Sending startup message");
-    let startup_message = send_startup_message(&http, &webhooks.logs).await?;
+    let startup_message = if let Some(logs) = &webhooks.logs {
+        Some(send_startup_message(&http, logs).await?)
+    } else {
+        None
+    };
 
     println!("Spawning analytics handler");
     let analytics = Arc::new(analytics::Handler::new(pool.clone()));
     tokio::spawn(analytics.clone().start());
 
+    if !tts_endpoints.is_empty() {
+        println!("Spawning tts-service health checker");
+        tokio::spawn(tts_endpoints.clone().start());
+    }
+
     let data = Arc::new(Data {
         pool,
         system_info: Mutex::new(sysinfo::System::new()),
         bot_list_tokens: Mutex::new(config.bot_list_tokens),
 
         fully_started: AtomicBool::new(false),
+        shutting_down: AtomicBool::new(false),
+        messages_in_flight: AtomicUsize::new(0),
         join_vc_tokens: dashmap::DashMap::new(),
         songbird: songbird::Songbird::serenity(),
         last_to_xsaid_tracker: dashmap::DashMap::new(),
@@ -137,22 +199,26 @@ async fn main_(start_time: std::time::SystemTime) -> Result<()> {
             .time_to_live(Duration::from_secs(60 * 60))
             .build(),
 
-        gtts_voices,
-        espeak_voices,
-        translation_languages,
-        gcloud_voices: prepare_gcloud_voices(gcloud_voices),
-        polly_voices: polly_voices
-            .into_iter()
-            .map(|v| (v.id.clone(), v))
-            .collect::<BTreeMap<_, _>>(),
+        voice_lists: RwLock::new(Arc::new(voice_lists)),
+
+        config_reloader: Box::new(reload::Reloader {
+            http: http.clone(),
+            logger: logger.clone(),
+            tts_endpoints: tts_endpoints.clone(),
+            startup_config: config_toml,
+        }),
 
         config: config.main,
-        premium_config: config.premium,
+        premium_config: RwLock::new(config.premium.map(Arc::new)),
         website_info: Mutex::new(config.website_info),
         reqwest,
+        tts_backends,
+        mode_health: ModeHealth::default(),
+        request_limiter,
+        mode_kill_switches,
         premium_avatar_url: FixedString::from_string_trunc(premium_user.face()),
         analytics,
-        webhooks,
+        webhooks: RwLock::new(Arc::new(webhooks)),
         start_time,
         startup_message,
         regex_cache: RegexCache::new()?,
@@ -163,6 +229,14 @@ async fn main_(start_time: std::time::SystemTime) -> Result<()> {
         guild_voice_db,
     });
 
+    println!("Spawning voice list refresher");
+    tokio::spawn(tts_tasks::voice_refresher::VoiceListRefresher(data.clone()).start());
+    // SQLite can only be used by a single process, so there are no other caches to invalidate.
+    #[cfg(not(feature = "sqlite"))]
+    tokio::spawn(tts_tasks::cache_invalidator::CacheInvalidator(data.clone()).start());
+    #[cfg(unix)]
+    tokio::spawn(reload::reload_on_hangup(data.clone()));
+
     let framework_options = poise::FrameworkOptions {
         commands: tts_commands::commands(),
         event_handler: |fw_ctx, event| Box::pin(tts_events::listen(fw_ctx, event)),
@@ -186,19 +260,50 @@ async fn main_(start_time: std::time::SystemTime) -> Result<()> {
         ..poise::FrameworkOptions::default()
     };
 
-    let mut client = serenity::ClientBuilder::new_with_http(http, tts_events::get_intents())
-        .voice_manager::<songbird::Songbird>(data.songbird.clone())
-        .framework(poise::Framework::new(framework_options))
-        .data(data as _)
-        .await?;
+    let mut client =
+        serenity::ClientBuilder::new_with_http(http.clone(), tts_events::get_intents())
+            .voice_manager::<songbird::Songbird>(data.songbird.clone())
+            .framework(poise::Framework::new(framework_options))
+            .data(data.clone() as _)
+            .await?;
 
+    let cache = client.cache.clone();
     let shard_manager = client.shard_manager.clone();
 
+    if let Some(address) = data.config.metrics_address {
+        let listener = tokio::net::TcpListener::bind(address)
+            .await
+            .with_context(|| format!("Failed to bind metrics_address {address}"))?;
+
+        println!("Serving metrics on {address}");
+        tokio::spawn(http_server::serve_metrics(
+            listener,
+            data.clone(),
+            cache.clone(),
+            shard_manager.clone(),
+        ));
+    }
+
+    if let Some(address) = data.config.health_address {
+        let listener = tokio::net::TcpListener::bind(address)
+            .await
+            .with_context(|| format!("Failed to bind health_address {address}"))?;
+
+        println!("Serving health checks on {address}");
+        tokio::spawn(http_server::serve_health(
+            listener,
+            data.clone(),
+            shard_manager.clone(),
+            tts_endpoints,
+        ));
+    }
+
     tokio::spawn(async move {
         wait_until_shutdown().await;
 
         tracing::warn!("Recieved control C and shutting down.");
-        shard_manager.shutdown_all().await;
+        let bot_id = cache.current_user().id;
+        shutdown::graceful_shutdown(&data, &http, bot_id, &shard_manager, &logger).await;
     });
 
     client.start_autosharded().await.map_err(Into::into)
@@ -208,8 +313,7 @@ async fn main_(start_time: std::time::SystemTime) -> Result<()> {
 async fn wait_until_shutdown() {
     use tokio::signal::unix as signal;
 
-    let [mut s1, mut s2, mut s3] = [
-        signal::signal(signal::SignalKind::hangup()).unwrap(),
+    let [mut s1, mut s2] = [
         signal::signal(signal::SignalKind::interrupt()).unwrap(),
         signal::signal(signal::SignalKind::terminate()).unwrap(),
     ];
@@ -217,7 +321,6 @@ async fn wait_until_shutdown() {
     tokio::select!(
         v = s1.recv() => v.unwrap(),
         v = s2.recv() => v.unwrap(),
-        v = s3.recv() => v.unwrap(),
     );
 }
 
@@ -0,0 +1,115 @@
+use std::sync::Arc;
+
+use poise::serenity_prelude as serenity;
+
+use tts_core::{
+    backends::Endpoints,
+    reload::{ConfigReloader, ReloadReport},
+    structs::{Data, Result},
+};
+use tts_tasks::logging::WebhookLogger;
+
+use crate::startup::{get_webhooks, log_filter, tts_service_configs};
+
+/// If a changed config key is swapped in by a reload, instead of needing a restart.
+fn is_reloadable(section: &str, key: &str) -> bool {
+    match section {
+        "Webhook-Info" | "Bot-List-Tokens" | "Website-Info" | "Premium-Info" | "TTS-Service" => {
+            true
+        }
+        "Main" => matches!(key, "log_level" | "tts_service" | "tts_service_auth_key"),
+        "Logging" => key == "targets",
+        _ => false,
+    }
+}
+
+/// Lists the keys that differ between the configs, but are only read at startup.
+fn changed_startup_keys(old: &toml::Table, new: &toml::Table) -> Vec<String> {
+    let mut changed = Vec::new();
+    let sections = old
+        .keys()
+        .chain(new.keys().filter(|key| !old.contains_key(*key)));
+    for section in sections {
+        let (old_value, new_value) = (old.get(section), new.get(section));
+        if old_value == new_value {
+            continue;
+        }
+
+        if let Some(toml::Value::Table(old_table)) = old_value
+            && let Some(toml::Value::Table(new_table)) = new_value
+        {
+            let keys = old_table
+                .keys()
+                .chain(new_table.keys().filter(|key| !old_table.contains_key(*key)));
+
+            for key in keys {
+                if old_table.get(key) != new_table.get(key) && !is_reloadable(section, key) {
+                    changed.push(format!("{section}.{key}"));
+                }
+            }
+        } else if !is_reloadable(section, "") {
+            changed.push(section.clone());
+        }
+    }
+
+    changed
+}
+
+pub struct Reloader {
+    pub http: Arc<serenity::Http>,
+    pub logger: Arc<WebhookLogger>,
+    pub tts_endpoints: Arc<Endpoints>,
+    /// The config the bot was started with, to report changes that need a restart.
+    pub startup_config: toml::Table,
+}
+
+#[serenity::async_trait]
+impl ConfigReloader for Reloader {
+    async fn reload(&self, data: &Data) -> Result<ReloadReport> {
+        let config_toml = tts_migrations::load_config()?;
+        let config = tts_migrations::parse_config(config_toml.clone())?;
+
+        // Everything that can fail is done first, so a broken config leaves the old one in place.
+        let log_filter = log_filter(&config.main, &config.logging)?;
+        let webhooks = get_webhooks(&self.http, config.webhooks).await?;
+
+        let mut needs_restart = changed_startup_keys(&self.startup_config, &config_toml);
+        let tts_services = tts_service_configs(&config.main, config.tts_services);
+        if tts_services.is_empty() == self.tts_endpoints.is_empty() {
+            let auth_key = config.main.tts_service_auth_key;
+            self.tts_endpoints.reload(tts_services, auth_key);
+        } else {
+            // The tts-service backend is only set up at startup, if there are any instances.
+            needs_restart.push(String::from("TTS-Service"));
+        }
+
+        self.logger.set_filter(log_filter);
+        self.logger
+            .set_webhooks(webhooks.logs.clone(), webhooks.errors.clone());
+
+        *data.webhooks.write() = Arc::new(webhooks);
+        *data.premium_config.write() = config.premium.map(Arc::new);
+        *data.website_info.lock() = config.website_info;
+        *data.bot_list_tokens.lock() = config.bot_list_tokens;
+
+        Ok(ReloadReport { needs_restart })
+    }
+}
+
+/// Reloads the config each time the process receives SIGHUP.
+#[cfg(unix)]
+pub async fn reload_on_hangup(data: Arc<Data>) {
+    use tokio::signal::unix as signal;
+
+    let mut hangup = signal::signal(signal::SignalKind::hangup()).unwrap();
+    while hangup.recv().await.is_some() {
+        match data.config_reloader.reload(&data).await {
+            Ok(report) if report.needs_restart.is_empty() => tracing::info!("Reloaded the config"),
+            Ok(report) => tracing::warn!(
+                "Reloaded the config, but these changes need a restart: {}",
+                report.needs_restart.join(", ")
+            ),
+            Err(err) => tracing::error!("Failed to reload the config: {err:?}"),
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use std::{
+    sync::{atomic::Ordering, Arc},
+    time::Duration,
+};
+
+use poise::serenity_prelude as serenity;
+
+use tts_core::{
+    backends::SynthesisRequest,
+    common::audio_to_input,
+    structs::{Data, Result},
+    tasks::TASKS,
+};
+use tts_tasks::{logging::WebhookLogger, Looper as _};
+
+const DEFAULT_GRACE_SECS: u64 = 10;
+
+type CallLock = Arc<tokio::sync::Mutex<songbird::Call>>;
+
+fn active_calls(data: &Data) -> Vec<(serenity::GuildId, CallLock)> {
+    data.songbird
+        .iter()
+        .map(|(guild_id, call)| (serenity::GuildId::new(guild_id.0.get()), call))
+        .collect()
+}
+
+async fn announce_restart(
+    data: &Data,
+    http: &serenity::Http,
+    bot_id: serenity::UserId,
+    guild_id: serenity::GuildId,
+    call: &CallLock,
+    notice: &str,
+) -> Result<()> {
+    let guild_row = data.guilds_db.get(guild_id.into()).await?;
+    let (voice, mode) = data
+        .parse_user_or_guild(http, bot_id, Some(guild_id))
+        .await?;
+    let speaking_rate = data
+        .speaking_rate_info(mode)
+        .map(|info| info.default)
+        .unwrap_or("1.0");
+
+    let max_length = guild_row.msg_length.to_string();
+    let request = SynthesisRequest {
+        mode,
+        text: notice,
+        voice: &voice,
+        speaking_rate,
+        max_length: &max_length,
+        translation_lang: None,
+    };
+
+    let Some(audio) = data.synthesise(request).await? else {
+        return Ok(());
+    };
+
+    let input = audio_to_input(audio);
+    call.lock().await.enqueue_input(input).await;
+    Ok(())
+}
+
+async fn wait_for_messages(data: &Data) {
+    while data.messages_in_flight.load(Ordering::SeqCst) != 0 {
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+}
+
+async fn wait_for_queues(calls: &[(serenity::GuildId, CallLock)]) {
+    loop {
+        let mut all_empty = true;
+        for (_, call) in calls {
+            if !call.lock().await.queue().is_empty() {
+                all_empty = false;
+                break;
+            }
+        }
+
+        if all_empty {
+            break;
+        }
+
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+}
+
+/// Stops accepting new messages, lets the accepted messages and current queues finish within
+/// the grace period, then stops background tasks and flushes analytics and logs before
+/// disconnecting all shards.
+///
+/// Voice sessions are kept, so they are resumed once the bot has restarted.
+pub async fn graceful_shutdown(
+    data: &Data,
+    http: &serenity::Http,
+    bot_id: serenity::UserId,
+    shard_manager: &serenity::ShardManager,
+    logger: &Arc<WebhookLogger>,
+) {
+    data.shutting_down.store(true, Ordering::SeqCst);
+
+    let grace_secs = data
+        .config
+        .shutdown_grace_secs
+        .unwrap_or(DEFAULT_GRACE_SECS);
+
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(grace_secs);
+
+    // Messages that were accepted before shutdown can still queue audio, or join a channel.
+    if tokio::time::timeout_at(deadline, wait_for_messages(data))
+        .await
+        .is_err()
+    {
+        tracing::warn!(
+            "Timed out waiting for {} messages to be queued",
+            data.messages_in_flight.load(Ordering::SeqCst)
+        );
+    }
+
+    let calls = active_calls(data);
+    if let Some(notice) = &data.config.restart_notice {
+        let announcements = calls.iter().map(|(guild_id, call)| async move {
+            if let Err(err) = announce_restart(data, http, bot_id, *guild_id, call, notice).await {
+                tracing::warn!("Failed to announce restart in {guild_id}: {err:?}");
+            }
+        });
+
+        serenity::futures::future::join_all(announcements).await;
+    }
+
+    if tokio::time::timeout_at(deadline, wait_for_queues(&calls))
+        .await
+        .is_err()
+    {
+        tracing::warn!(
+            "Timed out waiting for {} voice queues to drain",
+            calls.len()
+        );
+    }
+
+    // The final flushes below are run here, so the tasks only have to finish their current run.
+    if !TASKS.cancel(Duration::from_secs(5)).await {
+        tracing::warn!("Timed out waiting for background tasks to stop");
+    }
+
+    if let Err(err) = data.analytics.loop_func().await {
+        tracing::error!("Failed to flush analytics on shutdown: {err:?}");
+    }
+
+    // Flush the logger last, so everything above is sent.
+    if let Err(err) = logger.loop_func().await {
+        tracing::error!("Failed to flush logs on shutdown: {err:?}");
+    }
+
+    logger.shutdown_tracing().await;
+
+    shard_manager.shutdown_all().await;
+}
@@ -1,21 +1,85 @@
-use std::collections::BTreeMap;
-
-use small_fixed_array::{FixedString, TruncatingInto as _};
+use std::time::Duration;
 
 use poise::serenity_prelude as serenity;
 
 use tts_core::{
     opt_ext::OptionTryUnwrap as _,
-    structs::{GoogleGender, GoogleVoice, Result, TTSMode, WebhookConfig, WebhookConfigRaw},
+    structs::{
+        LoggingConfig, MainConfig, Result, TTSServiceConfig, WebhookConfig, WebhookConfigRaw,
+    },
 };
+use tts_tasks::logging::LogFilter;
+
+pub fn build_reqwest(main_config: &MainConfig) -> Result<reqwest::Client> {
+    let client = reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(
+            main_config.connect_timeout_secs.unwrap_or(5),
+        ))
+        .read_timeout(Duration::from_secs(
+            main_config.read_timeout_secs.unwrap_or(30),
+        ))
+        .build()?;
+
+    Ok(client)
+}
+
+pub fn build_http(main_config: &MainConfig, token: &str) -> serenity::Http {
+    let mut http_builder = serenity::HttpBuilder::new(token);
+    if let Some(proxy) = &main_config.proxy_url {
+        println!("Connecting via proxy");
+        http_builder = http_builder
+            .proxy(proxy.as_str())
+            .ratelimiter_disabled(true);
+    }
+
+    http_builder.build()
+}
+
+fn parse_level(level: &str) -> Result<tracing::Level> {
+    level
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Unknown log level: {level}"))
+}
+
+/// Combines `log_level` in `[Main]` with the per-target levels in `[Logging]`.
+pub fn log_filter(main_config: &MainConfig, logging: &LoggingConfig) -> Result<LogFilter> {
+    let level = match &main_config.log_level {
+        Some(level) => parse_level(level)?,
+        None => tracing::Level::INFO,
+    };
+
+    let targets = logging
+        .targets
+        .iter()
+        .map(|(target, level)| anyhow::Ok((target.clone(), parse_level(level)?)))
+        .collect::<Result<_>>()?;
+
+    Ok(LogFilter::new(level, targets))
+}
+
+/// Combines `tts_service` in `[Main]` with the `[[TTS-Service]]` instances.
+pub fn tts_service_configs(
+    main_config: &MainConfig,
+    mut tts_services: Vec<TTSServiceConfig>,
+) -> Vec<TTSServiceConfig> {
+    if let Some(url) = main_config.tts_service.clone() {
+        tts_services.insert(0, TTSServiceConfig { url, weight: None });
+    }
+
+    tts_services
+}
 
 pub async fn get_webhooks(
     http: &serenity::Http,
     webhooks_raw: WebhookConfigRaw,
 ) -> Result<WebhookConfig> {
-    let get_webhook = |url: reqwest::Url| async move {
+    let get_webhook = |url: Option<reqwest::Url>| async move {
+        let Some(url) = url else {
+            return Ok(None);
+        };
+
         let (webhook_id, token) = serenity::parse_webhook(&url).try_unwrap()?;
-        anyhow::Ok(http.get_webhook_with_token(webhook_id, token).await?)
+        anyhow::Ok(Some(http.get_webhook_with_token(webhook_id, token).await?))
     };
 
     let (logs, errors, dm_logs) = tokio::try_join!(
@@ -32,86 +96,6 @@ pub async fn get_webhooks(
     })
 }
 
-async fn fetch_json<T>(reqwest: &reqwest::Client, url: reqwest::Url, auth_header: &str) -> Result<T>
-where
-    T: serde::de::DeserializeOwned,
-{
-    let resp = reqwest
-        .get(url)
-        .header("Authorization", auth_header)
-        .send()
-        .await?
-        .error_for_status()?
-        .json()
-        .await?;
-
-    Ok(resp)
-}
-
-pub async fn fetch_voices<T: serde::de::DeserializeOwned>(
-    reqwest: &reqwest::Client,
-    mut tts_service: reqwest::Url,
-    auth_key: Option<&str>,
-    mode: TTSMode,
-) -> Result<T> {
-    tts_service.set_path("voices");
-    tts_service
-        .query_pairs_mut()
-        .append_pair("mode", mode.into())
-        .append_pair("raw", "true")
-        .finish();
-
-    let res = fetch_json(reqwest, tts_service, auth_key.unwrap_or("")).await?;
-
-    println!("Loaded voices for TTS Mode: {mode}");
-    Ok(res)
-}
-
-pub async fn fetch_translation_languages(
-    reqwest: &reqwest::Client,
-    mut tts_service: reqwest::Url,
-    auth_key: Option<&str>,
-) -> Result<BTreeMap<FixedString<u8>, FixedString<u8>>> {
-    tts_service.set_path("translation_languages");
-
-    let raw_langs: Vec<(String, FixedString<u8>)> =
-        fetch_json(reqwest, tts_service, auth_key.unwrap_or("")).await?;
-
-    let lang_map = raw_langs.into_iter().map(|(mut lang, name)| {
-        lang.make_ascii_lowercase();
-        (lang.trunc_into(), name)
-    });
-
-    println!("Loaded DeepL translation languages");
-    Ok(lang_map.collect())
-}
-
-pub fn prepare_gcloud_voices(
-    raw_map: Vec<GoogleVoice>,
-) -> BTreeMap<FixedString<u8>, BTreeMap<FixedString<u8>, GoogleGender>> {
-    // {lang_accent: {variant: gender}}
-    let mut cleaned_map = BTreeMap::new();
-    for gvoice in raw_map {
-        let variant = gvoice
-            .name
-            .splitn(3, '-')
-            .nth(2)
-            .and_then(|mode_variant| mode_variant.split_once('-'))
-            .filter(|(mode, _)| *mode == "Standard")
-            .map(|(_, variant)| variant);
-
-        if let Some(variant) = variant {
-            let [language] = gvoice.language_codes;
-            cleaned_map
-                .entry(language)
-                .or_insert_with(BTreeMap::new)
-                .insert(FixedString::from_str_trunc(variant), gvoice.ssml_gender);
-        }
-    }
-
-    cleaned_map
-}
-
 pub async fn send_startup_message(
     http: &serenity::Http,
     log_webhook: &serenity::Webhook,
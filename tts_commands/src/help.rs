@@ -94,6 +94,7 @@ pub async fn autocomplete(ctx: ApplicationContext<'_>, searching: &str) -> Vec<S
     slash_command,
     required_bot_permissions = "SEND_MESSAGES | EMBED_LINKS"
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 async fn help(
     ctx: Context<'_>,
     #[rest]
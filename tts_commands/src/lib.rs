@@ -9,7 +9,9 @@ use serenity::all::{self as serenity, Mentionable as _};
 use tts_core::{
     constants::PREMIUM_NEUTRAL_COLOUR,
     opt_ext::OptionTryUnwrap as _,
-    structs::{Command, Context, FailurePoint, PartialContext, Result},
+    structs::{
+        ApplicationContext, Command, Context, Data, FailurePoint, PartialContext, Result, TTSMode,
+    },
     traits::PoiseContextExt,
 };
 
@@ -100,6 +102,53 @@ pub async fn premium_command_check(ctx: Context<'_>) -> Result<bool> {
     Ok(false)
 }
 
+/// Explains each globally disabled mode that could be used by the guild.
+#[cold]
+fn mode_disabled_embeds(data: &Data, is_premium: bool) -> Vec<serenity::CreateEmbed<'static>> {
+    let footer = format!("Support server: {}", data.config.main_server_invite);
+
+    data.mode_kill_switches
+        .iter()
+        .filter(|kill_switch| is_premium || !kill_switch.key().is_premium())
+        .map(|kill_switch| {
+            let mode = kill_switch.key();
+            let fallback = kill_switch.fallback;
+
+            serenity::CreateEmbed::default()
+                .title(format!("The `{mode}` voice mode is globally disabled"))
+                .description(format!(
+                    "Any usage of this mode will instead use the `{fallback}` mode.\nReason: {}",
+                    kill_switch.reason
+                ))
+                .footer(serenity::CreateEmbedFooter::new(footer.clone()))
+                .colour(tts_core::constants::RED)
+        })
+        .collect()
+}
+
+#[cold]
+fn mode_disabled_error(data: &Data, mode: TTSMode) -> Option<String> {
+    let kill_switch = data.mode_kill_switches.get(&mode)?;
+    Some(format!(
+        "The `{mode}` voice mode is currently disabled so cannot be used, reason: {}",
+        kill_switch.reason
+    ))
+}
+
+#[allow(clippy::unused_async)]
+async fn mode_autocomplete<'a>(
+    _ctx: ApplicationContext<'a>,
+    searching: &'a str,
+) -> impl Iterator<Item = serenity::AutocompleteChoice<'a>> {
+    let searching = searching.to_lowercase();
+    TTSMode::all()
+        .map(|mode| (mode.display_name(), mode.db_name()))
+        .filter(move |(name, value)| {
+            name.to_lowercase().contains(&searching) || value.contains(&searching)
+        })
+        .map(|(name, value)| serenity::AutocompleteChoice::new(name, value))
+}
+
 pub async fn get_prefix(ctx: PartialContext<'_>) -> Result<Option<Cow<'static, str>>> {
     let Some(guild_id) = ctx.guild_id else {
         return Ok(Some(Cow::Borrowed("-")));
@@ -1,4 +1,4 @@
-use std::sync::{atomic::Ordering, Arc};
+use std::sync::Arc;
 
 use aformat::{aformat, ArrayString};
 
@@ -7,14 +7,16 @@ use songbird::error::JoinError;
 
 use tts_core::{
     common::{push_permission_names, random_footer},
-    constants::RED,
     database_models::GuildRow,
     opt_ext::OptionTryUnwrap as _,
     require_guild,
     structs::{Command, CommandResult, Context, JoinVCToken, Result},
     traits::{PoiseContextExt, SongbirdManagerExt},
+    voice_sessions,
 };
 
+use crate::mode_disabled_embeds;
+
 /// Returns Some(GuildRow) on correct channel, otherwise None.
 async fn channel_check(
     ctx: &Context<'_>,
@@ -50,20 +52,6 @@ fn create_warning_embed<'a>(title: &'a str, footer: &'a str) -> serenity::Create
         .footer(serenity::CreateEmbedFooter::new(footer))
 }
 
-#[cold]
-fn gtts_disabled_embed<'a>(
-    msg: poise::CreateReply<'a>,
-    support_server: &'a str,
-) -> poise::CreateReply<'a> {
-    msg.embed(
-        serenity::CreateEmbed::default()
-            .title("The `gTTS` voice mode is globally disabled due to maintainance")
-            .description("Any usage of this mode will instead use the lower quality `eSpeak` mode, while premium modes are unaffected.")
-            .footer(serenity::CreateEmbedFooter::new(format!("Support server: {support_server}")))
-            .colour(RED)
-    )
-}
-
 #[cold]
 fn required_prefix_embed<'a>(
     title_place: &'a mut ArrayString<46>,
@@ -105,6 +93,7 @@ fn required_role_embed<'a>(
     slash_command,
     required_bot_permissions = "SEND_MESSAGES | EMBED_LINKS"
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn join(ctx: Context<'_>) -> CommandResult {
     let Some(author_vc) = ctx.author_vc() else {
         let err = "I cannot join your voice channel unless you are in one!";
@@ -165,8 +154,7 @@ pub async fn join(ctx: Context<'_>) -> CommandResult {
                 return Ok(());
             } else {
                 tracing::warn!("Channel {bot_channel_id} didn't exist in {guild_id} in `/join`");
-                data.last_to_xsaid_tracker.remove(&channel.guild_id);
-                data.songbird.remove(guild_id).await?;
+                data.leave_vc(guild_id).await?;
             }
         }
     };
@@ -187,6 +175,8 @@ pub async fn join(ctx: Context<'_>) -> CommandResult {
             };
         };
 
+        voice_sessions::save(&data.pool, guild_id, author_vc, ctx.channel_id()).await?;
+
         match ctx {
             Context::Application(poise::ApplicationContext { interaction, .. }) => {
                 interaction.member.as_deref().try_unwrap()?.display_name()
@@ -211,8 +201,8 @@ pub async fn join(ctx: Context<'_>) -> CommandResult {
     let mut msg = poise::CreateReply::default().embed(embed);
 
     // In-perfect premium check, but we don't need to be perfect
-    if data.config.gtts_disabled.load(Ordering::Relaxed) && guild_row.premium_user.is_none() {
-        msg = gtts_disabled_embed(msg, &data.config.main_server_invite);
+    for embed in mode_disabled_embeds(&data, guild_row.premium_user.is_some()) {
+        msg = msg.embed(embed);
     }
 
     let mut title_place = ArrayString::new();
@@ -237,6 +227,7 @@ pub async fn join(ctx: Context<'_>) -> CommandResult {
     slash_command,
     required_bot_permissions = "SEND_MESSAGES"
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn leave(ctx: Context<'_>) -> CommandResult {
     let (guild_id, author_vc) = {
         let guild = require_guild!(ctx);
@@ -264,8 +255,7 @@ pub async fn leave(ctx: Context<'_>) -> CommandResult {
             ctx.say("Error: You need to be in the same voice channel as me to make me leave!")
                 .await?;
         } else {
-            data.last_to_xsaid_tracker.remove(&guild_id);
-            data.songbird.remove(guild_id).await?;
+            data.leave_vc(guild_id).await?;
 
             ctx.say("Left voice channel!").await?;
         }
@@ -286,6 +276,7 @@ pub async fn leave(ctx: Context<'_>) -> CommandResult {
     slash_command,
     required_bot_permissions = "SEND_MESSAGES | ADD_REACTIONS"
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn clear(ctx: Context<'_>) -> CommandResult {
     if channel_check(&ctx, ctx.author_vc()).await?.is_none() {
         return Ok(());
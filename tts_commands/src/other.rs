@@ -1,4 +1,4 @@
-use aformat::{aformat, astr};
+use aformat::{aformat, CapStr};
 use anyhow::Error;
 use num_format::{Locale, ToFormattedString};
 
@@ -11,11 +11,11 @@ use poise::{
 
 use aformat::ToArrayString;
 use tts_core::{
-    common::{fetch_audio, prepare_url},
+    backends::SynthesisRequest,
     constants::OPTION_SEPERATORS,
     opt_ext::OptionTryUnwrap,
     require_guild,
-    structs::{ApplicationContext, Command, CommandResult, Context, IsPremium, TTSMode},
+    structs::{ApplicationContext, Command, CommandResult, Context, IsPremium},
     traits::PoiseContextExt as _,
 };
 
@@ -26,6 +26,7 @@ use tts_core::{
     slash_command,
     required_bot_permissions = "SEND_MESSAGES"
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn uptime(ctx: Context<'_>) -> CommandResult {
     let start_time = ctx.data().start_time;
     let time_since_start = start_time.duration_since(std::time::UNIX_EPOCH)?.as_secs();
@@ -45,6 +46,7 @@ pub async fn uptime(ctx: Context<'_>) -> CommandResult {
     slash_command,
     required_bot_permissions = "SEND_MESSAGES | ATTACH_FILES"
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn tts(
     ctx: Context<'_>,
     #[description = "The text to TTS"]
@@ -122,34 +124,26 @@ async fn _tts(ctx: Context<'_>, author: &serenity::User, message: &str) -> Comma
             .collect();
         let speaking_rate = data.speaking_rate(author.id, mode).await?;
 
-        let url = prepare_url(
-            data.config.tts_service.clone(),
-            message,
-            &voice,
+        let max_length = u64::MAX.to_arraystring();
+        let request = SynthesisRequest {
             mode,
-            &speaking_rate,
-            &u64::MAX.to_arraystring(),
+            text: message,
+            voice: &voice,
+            speaking_rate: &speaking_rate,
+            max_length: &max_length,
             translation_lang,
-        );
+        };
 
-        let auth_key = data.config.tts_service_auth_key.as_deref();
-        let audio = fetch_audio(&data.reqwest, url, auth_key)
-            .await?
-            .try_unwrap()?
-            .bytes()
-            .await?;
+        let audio = data.synthesise(request).await?.try_unwrap()?;
 
         let mut file_name = author_name;
         file_name.push_str(&aformat!(
             "-{}.{}",
             ctx.id(),
-            match mode {
-                TTSMode::gTTS | TTSMode::gCloud | TTSMode::Polly => astr!("mp3"),
-                TTSMode::eSpeak => astr!("wav"),
-            }
+            CapStr::<3>(audio.file_extension())
         ));
 
-        serenity::CreateAttachment::bytes(audio.to_vec(), file_name)
+        serenity::CreateAttachment::bytes(audio.into_bytes().await?, file_name)
     };
 
     ctx.send(
@@ -167,6 +161,7 @@ async fn _tts(ctx: Context<'_>, author: &serenity::User, message: &str) -> Comma
     hide_in_help,
     context_menu_command = "Speak with their voice!"
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn tts_speak_as(
     ctx: ApplicationContext<'_>,
     message: serenity::Message,
@@ -179,6 +174,7 @@ pub async fn tts_speak_as(
     hide_in_help,
     context_menu_command = "Speak with your voice!"
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn tts_speak(ctx: ApplicationContext<'_>, message: serenity::Message) -> CommandResult {
     _tts(ctx.into(), &ctx.interaction.user, &message.content).await
 }
@@ -190,6 +186,7 @@ pub async fn tts_speak(ctx: ApplicationContext<'_>, message: serenity::Message)
     slash_command,
     required_bot_permissions = "SEND_MESSAGES | EMBED_LINKS"
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn botstats(ctx: Context<'_>) -> CommandResult {
     let data = ctx.data();
     let cache = ctx.cache();
@@ -239,6 +236,13 @@ pub async fn botstats(ctx: Context<'_>) -> CommandResult {
         (title, thumbnail)
     };
 
+    let in_flight = data.request_limiter.in_flight();
+    let queue_depth = data.request_limiter.queue_depth();
+
+    let db_cache_stats = data.db_cache_stats();
+    let db_hit_rate = db_cache_stats.hit_rate();
+    let db_evictions = db_cache_stats.evictions;
+
     let time_to_fetch = start_time.elapsed()?.as_secs_f64() * 1000.0;
     let embed = CreateEmbed::default()
         .title(embed_title)
@@ -258,6 +262,8 @@ Repository: https://github.com/Discord-TTS/Bot",
 Currently using:
 {sep1} {shard_count} shards
 {sep1} {ram_usage:.1}MB of RAM
+{sep1} {in_flight} TTS requests ({queue_depth} queued)
+{sep1} {db_hit_rate:.1}% database cache hit rate ({db_evictions} evictions)
 and can be used by {total_members} people!",
         ));
 
@@ -273,6 +279,7 @@ and can be used by {total_members} people!",
     slash_command,
     required_bot_permissions = "SEND_MESSAGES"
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn channel(ctx: Context<'_>) -> CommandResult {
     let guild_id = ctx.guild_id().unwrap();
     let guild_row = ctx.data().guilds_db.get(guild_id.into()).await?;
@@ -301,6 +308,7 @@ pub async fn channel(ctx: Context<'_>) -> CommandResult {
     required_bot_permissions = "SEND_MESSAGES",
     aliases("lag")
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn ping(ctx: Context<'_>) -> CommandResult {
     let ping_before = std::time::SystemTime::now();
     let ping_msg = ctx.say("Loading!").await?;
@@ -321,6 +329,7 @@ pub async fn ping(ctx: Context<'_>) -> CommandResult {
     slash_command,
     required_bot_permissions = "SEND_MESSAGES"
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn invite(ctx: Context<'_>) -> CommandResult {
     let cache = ctx.cache();
     let config = &ctx.data().config;
@@ -1,4 +1,9 @@
-use std::{borrow::Cow, hash::Hash, sync::Arc};
+use std::{
+    borrow::Cow,
+    fmt::Write as _,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use aformat::aformat;
 use num_format::{Locale, ToFormattedString};
@@ -11,16 +16,20 @@ use tts_core::{
     common::dm_generic,
     database,
     database_models::Compact,
-    structs::{Command, CommandResult, Context, PrefixContext, TTSModeChoice},
+    structs::{Command, CommandResult, Context, PrefixContext, TTSMode},
+    tasks::TASKS,
+    voice_lists,
 };
 
 #[poise::command(prefix_command, owners_only, hide_in_help)]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn register(ctx: Context<'_>) -> CommandResult {
     poise::samples::register_application_commands(ctx, true).await?;
     Ok(())
 }
 
 #[poise::command(prefix_command, hide_in_help, owners_only)]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn dm(
     ctx: PrefixContext<'_>,
     todm: serenity::User,
@@ -57,12 +66,14 @@ pub async fn dm(
     aliases("invalidate_cache", "delete_cache"),
     subcommands("guild", "user", "guild_voice", "user_voice")
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn remove_cache(ctx: Context<'_>) -> CommandResult {
     ctx.say("Please run a subcommand!").await?;
     Ok(())
 }
 
 #[poise::command(prefix_command, owners_only, hide_in_help)]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn guild(ctx: Context<'_>, guild: i64) -> CommandResult {
     ctx.data().guilds_db.invalidate_cache(&guild);
     ctx.say("Done!").await?;
@@ -70,6 +81,7 @@ pub async fn guild(ctx: Context<'_>, guild: i64) -> CommandResult {
 }
 
 #[poise::command(prefix_command, owners_only, hide_in_help)]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn user(ctx: Context<'_>, user: i64) -> CommandResult {
     ctx.data().userinfo_db.invalidate_cache(&user);
     ctx.say("Done!").await?;
@@ -77,24 +89,23 @@ pub async fn user(ctx: Context<'_>, user: i64) -> CommandResult {
 }
 
 #[poise::command(prefix_command, owners_only, hide_in_help)]
-pub async fn guild_voice(ctx: Context<'_>, guild: i64, mode: TTSModeChoice) -> CommandResult {
-    ctx.data()
-        .guild_voice_db
-        .invalidate_cache(&(guild, mode.into()));
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
+pub async fn guild_voice(ctx: Context<'_>, guild: i64, mode: TTSMode) -> CommandResult {
+    ctx.data().guild_voice_db.invalidate_cache(&(guild, mode));
     ctx.say("Done!").await?;
     Ok(())
 }
 
 #[poise::command(prefix_command, owners_only, hide_in_help)]
-pub async fn user_voice(ctx: Context<'_>, user: i64, mode: TTSModeChoice) -> CommandResult {
-    ctx.data()
-        .user_voice_db
-        .invalidate_cache(&(user, mode.into()));
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
+pub async fn user_voice(ctx: Context<'_>, user: i64, mode: TTSMode) -> CommandResult {
+    ctx.data().user_voice_db.invalidate_cache(&(user, mode));
     ctx.say("Done!").await?;
     Ok(())
 }
 
 #[poise::command(prefix_command, owners_only, hide_in_help)]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn refresh_ofs(ctx: Context<'_>) -> CommandResult {
     let data = ctx.data();
     let http = &ctx.http();
@@ -151,12 +162,14 @@ pub async fn refresh_ofs(ctx: Context<'_>) -> CommandResult {
     guild_only,
     subcommands("info", "leave")
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn debug(ctx: Context<'_>) -> CommandResult {
     _info(ctx).await
 }
 
 /// Shows debug information including voice info and database info.
 #[poise::command(prefix_command, slash_command, guild_only)]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn info(ctx: Context<'_>) -> CommandResult {
     _info(ctx).await
 }
@@ -204,13 +217,10 @@ Guild Voice Data: `{guild_voice_row:?}`
 
 /// Force leaves the voice channel in the current server to bypass buggy states
 #[poise::command(prefix_command, guild_only, hide_in_help)]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn leave(ctx: Context<'_>) -> CommandResult {
     let guild_id = ctx.guild_id().unwrap();
-    ctx.data()
-        .songbird
-        .remove(guild_id)
-        .await
-        .map_err(Into::into)
+    ctx.data().leave_vc(guild_id).await
 }
 
 fn get_db_info<CacheKey, RowT>(
@@ -218,7 +228,7 @@ fn get_db_info<CacheKey, RowT>(
     handler: &database::Handler<CacheKey, RowT>,
 ) -> typesize::Field
 where
-    CacheKey: Eq + Hash + TypeSize,
+    CacheKey: database::CacheKeyTrait + TypeSize,
     RowT::Compacted: TypeSize,
     RowT: Compact,
 {
@@ -336,11 +346,21 @@ fn process_cache_info(
 }
 
 #[poise::command(prefix_command, owners_only)]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn cache_info(ctx: Context<'_>, kind: Option<String>) -> CommandResult {
     ctx.defer().await?;
 
+    let mut db_stats = Vec::new();
     let db_info = if kind.as_deref() == Some("db") {
         let data = ctx.data();
+        db_stats = vec![
+            ("guild db", data.guilds_db.stats()),
+            ("userinfo db", data.userinfo_db.stats()),
+            ("nickname db", data.nickname_db.stats()),
+            ("user voice db", data.user_voice_db.stats()),
+            ("guild voice db", data.guild_voice_db.stats()),
+        ];
+
         Some(vec![
             get_db_info("guild db", &data.guilds_db),
             get_db_info("userinfo db", &data.userinfo_db),
@@ -359,21 +379,134 @@ pub async fn cache_info(ctx: Context<'_>, kind: Option<String>) -> CommandResult
         return Ok(());
     };
 
+    let stats_fields = db_stats.into_iter().map(|(name, stats)| {
+        let value = format!(
+            "Hits: `{}`\nMisses: `{}`\nEvictions: `{}`\nHit rate: `{:.1}%`",
+            stats.hits,
+            stats.misses,
+            stats.evictions,
+            stats.hit_rate()
+        );
+
+        (format!("`{name}` usage"), value, true)
+    });
+
     let embed = CreateEmbed::default()
         .title("Cache Statistics")
-        .fields(fields.into_iter().take(25).map(|f| (f.name, f.value, true)));
+        .fields(fields.into_iter().take(20).map(|f| (f.name, f.value, true)))
+        .fields(stats_fields);
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+#[poise::command(prefix_command, owners_only, hide_in_help)]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
+pub async fn refresh_voices(ctx: Context<'_>) -> CommandResult {
+    let removed_voices = voice_lists::refresh(&ctx.data()).await?;
+    if removed_voices.is_empty() {
+        ctx.say("Done! No voices have been removed.").await?;
+        return Ok(());
+    }
+
+    // The full list of voices is logged, as it could be too long for a message.
+    let mut msg = String::from("Done! Some voices have been removed:");
+    for removed in removed_voices {
+        write!(
+            msg,
+            "\n`{}`: {} voices, selected by {} users and {} servers",
+            removed.mode,
+            removed.voices.len(),
+            removed.affected_users,
+            removed.affected_guilds
+        )?;
+    }
+
+    ctx.say(msg).await?;
+    Ok(())
+}
+
+#[poise::command(prefix_command, owners_only, hide_in_help)]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
+pub async fn reload_config(ctx: Context<'_>) -> CommandResult {
+    let data = ctx.data();
+    let report = data.config_reloader.reload(&data).await?;
+    if report.needs_restart.is_empty() {
+        ctx.say("Reloaded the config.").await?;
+    } else {
+        let keys = report.needs_restart.join("`, `");
+        let msg = format!("Reloaded the config, but these changes need a restart: `{keys}`");
+        ctx.say(msg).await?;
+    }
+
+    Ok(())
+}
+
+/// Formats a time as a Discord relative timestamp, such as "5 minutes ago".
+fn relative_time(time: Option<SystemTime>) -> Cow<'static, str> {
+    match time.and_then(|time| time.duration_since(UNIX_EPOCH).ok()) {
+        Some(since_epoch) => Cow::Owned(format!("<t:{}:R>", since_epoch.as_secs())),
+        None => Cow::Borrowed("Never"),
+    }
+}
+
+/// Shows the status of each background task
+#[poise::command(prefix_command, slash_command, owners_only, hide_in_help)]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
+pub async fn tasks(ctx: Context<'_>) -> CommandResult {
+    let fields = TASKS.snapshot().into_iter().map(|(name, status)| {
+        let state = if status.stopped {
+            "Stopped"
+        } else if status.running {
+            "Running"
+        } else if status.is_failing() {
+            "Failing"
+        } else {
+            "Waiting"
+        };
+
+        let mut value = format!(
+            "State: `{state}`\nRuns: `{}`\nLast run: {}\nLast success: {}\nNext run: {}",
+            status.runs,
+            relative_time(status.last_run),
+            relative_time(status.last_success),
+            relative_time(status.next_run),
+        );
+
+        if let Some(error) = status.last_error {
+            // Embed fields are limited to 1024 characters, so only the start of the error is shown.
+            let error: String = error
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .chars()
+                .take(200)
+                .collect();
+            let failures = status.consecutive_failures;
+            write!(value, "\nFailed {failures} runs in a row: `{error}`")?;
+        }
+
+        Ok::<_, std::fmt::Error>((format!("`{name}`"), value, true))
+    });
+
+    let embed = CreateEmbed::default()
+        .title("Background Tasks")
+        .fields(fields.collect::<Result<Vec<_>, _>>()?);
 
     ctx.send(CreateReply::default().embed(embed)).await?;
     Ok(())
 }
 
-pub fn commands() -> [Command; 6] {
+pub fn commands() -> [Command; 9] {
     [
         dm(),
         debug(),
         register(),
         remove_cache(),
         refresh_ofs(),
+        refresh_voices(),
+        reload_config(),
         cache_info(),
+        tasks(),
     ]
 }
@@ -11,6 +11,7 @@ use poise::{
 use tts_core::{
     common::remove_premium,
     constants::PREMIUM_NEUTRAL_COLOUR,
+    database::{self, GuildRow},
     opt_ext::OptionTryUnwrap as _,
     structs::{Command, CommandResult, Context, Result, TTSMode},
     traits::PoiseContextExt,
@@ -22,7 +23,7 @@ struct GuildIdRow {
 }
 
 fn get_premium_guilds<'a>(
-    conn: impl sqlx::PgExecutor<'a> + 'a,
+    conn: impl sqlx::Executor<'a, Database = database::Db> + 'a,
     premium_user: serenity::UserId,
 ) -> BoxStream<'a, Result<GuildIdRow, sqlx::Error>> {
     sqlx::query_as("SELECT guild_id FROM guilds WHERE premium_user = $1")
@@ -31,7 +32,7 @@ fn get_premium_guilds<'a>(
 }
 
 async fn get_premium_guild_count<'a>(
-    conn: impl sqlx::PgExecutor<'a> + 'a,
+    conn: impl sqlx::Executor<'a, Database = database::Db> + 'a,
     premium_user: serenity::UserId,
 ) -> Result<i64> {
     let guilds = get_premium_guilds(conn, premium_user);
@@ -46,8 +47,9 @@ async fn get_premium_guild_count<'a>(
     required_bot_permissions = "SEND_MESSAGES",
     aliases("purchase", "donate")
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn premium(ctx: Context<'_>) -> CommandResult {
-    let msg = if let Some(premium_config) = &ctx.data().premium_config {
+    let msg = if let Some(premium_config) = ctx.data().premium_config() {
         let patreon_url = premium_config.patreon_page_url;
         let application_id = ctx.http().application_id().try_unwrap()?;
         &aformat!(concat!(
@@ -73,6 +75,7 @@ pub async fn premium(ctx: Context<'_>) -> CommandResult {
     aliases("activate"),
     required_bot_permissions = "SEND_MESSAGES | EMBED_LINKS"
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn premium_activate(ctx: Context<'_>) -> CommandResult {
     let guild_id = ctx.guild_id().unwrap();
     let data = ctx.data();
@@ -114,12 +117,11 @@ pub async fn premium_activate(ctx: Context<'_>) -> CommandResult {
 
     let author_id = author.id.get() as i64;
     data.userinfo_db.create_row(author_id).await?;
-    data.guilds_db
-        .set_one(guild_id.into(), "premium_user", &author_id)
-        .await?;
-    data.guilds_db
-        .set_one(guild_id.into(), "voice_mode", &TTSMode::gCloud)
-        .await?;
+    let update = GuildRow::update()
+        .premium_user(Some(author_id))
+        .voice_mode(TTSMode::gCloud);
+
+    data.guilds_db.update(guild_id.into(), update).await?;
 
     ctx.say("Done! This server is now premium!").await?;
 
@@ -147,6 +149,7 @@ pub async fn premium_activate(ctx: Context<'_>) -> CommandResult {
     slash_command,
     required_bot_permissions = "SEND_MESSAGES | EMBED_LINKS"
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn list_premium(ctx: Context<'_>) -> CommandResult {
     let data = ctx.data();
     let Some(premium_info) = data.fetch_premium_info(ctx.http(), ctx.author().id).await? else {
@@ -194,6 +197,7 @@ pub async fn list_premium(ctx: Context<'_>) -> CommandResult {
     aliases("premium_remove", "premium_delete"),
     required_bot_permissions = "SEND_MESSAGES | EMBED_LINKS"
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn premium_deactivate(ctx: Context<'_>) -> CommandResult {
     let data = ctx.data();
     let author = ctx.author();
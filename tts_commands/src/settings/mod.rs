@@ -2,7 +2,7 @@ mod owner;
 mod setup;
 mod voice_paginator;
 
-use std::{borrow::Cow, collections::HashMap, fmt::Write, sync::atomic::Ordering};
+use std::{borrow::Cow, collections::HashMap, fmt::Write};
 
 use aformat::{aformat, ToArrayString};
 use arrayvec::ArrayString;
@@ -16,25 +16,31 @@ use serenity::{
 
 use tts_core::{
     common::{confirm_dialog, random_footer},
-    constants::{GTTS_DISABLED_ERROR, OPTION_SEPERATORS, PREMIUM_NEUTRAL_COLOUR},
+    constants::{OPTION_SEPERATORS, PREMIUM_NEUTRAL_COLOUR},
     database::{self, Compact},
+    opt_ext::OptionTryUnwrap as _,
     require_guild,
     structs::{
-        ApplicationContext, Command, CommandResult, Context, Data, Error, Result, SpeakingRateInfo,
-        TTSMode, TTSModeChoice,
+        ApplicationContext, Command, CommandResult, Context, Error, ModeList, Result,
+        SpeakingRateInfo, TTSMode,
     },
     traits::PoiseContextExt,
+    voice_lists::VoiceLists,
 };
 
 use self::voice_paginator::MenuPaginator;
+use crate::{mode_autocomplete, mode_disabled_embeds, mode_disabled_error};
 
-fn format_voice<'a>(data: &Data, voice: &'a str, mode: TTSMode) -> Cow<'a, str> {
-    if mode == TTSMode::gCloud {
-        let (lang, variant) = voice.split_once(' ').unwrap();
-        let gender = &data.gcloud_voices[lang][variant];
+fn format_voice<'a>(voices: &VoiceLists, voice: &'a str, mode: TTSMode) -> Cow<'a, str> {
+    // The voice may have been removed since it was selected, so fall back to the raw code.
+    if mode == TTSMode::gCloud
+        && let Some((lang, variant)) = voice.split_once(' ')
+        && let Some(gender) = voices.gcloud_voices.get(lang).and_then(|l| l.get(variant))
+    {
         Cow::Owned(format!("{lang} - {variant} ({gender})"))
-    } else if mode == TTSMode::Polly {
-        let voice = &data.polly_voices[voice];
+    } else if mode == TTSMode::Polly
+        && let Some(voice) = voices.polly_voices.get(voice)
+    {
         Cow::Owned(format!(
             "{} - {} ({})",
             voice.name, voice.language_name, voice.gender
@@ -52,6 +58,7 @@ fn format_voice<'a>(data: &Data, voice: &'a str, mode: TTSMode) -> Cow<'a, str>
     slash_command,
     required_bot_permissions = "SEND_MESSAGES | EMBED_LINKS"
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn settings(ctx: Context<'_>) -> CommandResult {
     let guild_id = ctx.guild_id().unwrap();
     let author_id = ctx.author().id;
@@ -82,7 +89,8 @@ pub async fn settings(ctx: Context<'_>) -> CommandResult {
         .required_role
         .map(|r| r.mention().to_arraystring());
 
-    let user_mode = if data.is_premium_simple(ctx.http(), guild_id).await? {
+    let is_premium = data.is_premium_simple(ctx.http(), guild_id).await?;
+    let user_mode = if is_premium {
         userinfo_row.premium_voice_mode
     } else {
         userinfo_row.voice_mode
@@ -92,11 +100,12 @@ pub async fn settings(ctx: Context<'_>) -> CommandResult {
         .guild_voice_db
         .get((guild_id.into(), guild_mode))
         .await?;
+    let voices = data.voices();
     let default_voice = {
         if guild_voice_row.guild_id.is_none() {
             Cow::Borrowed(guild_mode.default_voice())
         } else {
-            format_voice(&data, &guild_voice_row.voice, guild_mode)
+            format_voice(&voices, &guild_voice_row.voice, guild_mode)
         }
     };
 
@@ -109,14 +118,14 @@ pub async fn settings(ctx: Context<'_>) -> CommandResult {
             .await?;
 
         match user_voice_row.voice.as_ref() {
-            Some(voice) => format_voice(&data, voice, currently_set_voice_mode),
+            Some(voice) => format_voice(&voices, voice, currently_set_voice_mode),
             None => Cow::Borrowed(none_str),
         }
     };
 
     let (speaking_rate, speaking_rate_kind) = if let Some(mode) = user_mode {
         let user_voice_row = data.user_voice_db.get((author_id.into(), mode)).await?;
-        let (default, kind) = match mode.speaking_rate_info() {
+        let (default, kind) = match data.speaking_rate_info(mode) {
             Some(info) => (info.default, info.kind),
             None => ("1.0", "x"),
         };
@@ -141,6 +150,12 @@ pub async fn settings(ctx: Context<'_>) -> CommandResult {
     let bot_ignore = guild_row.bot_ignore();
     let skip_emoji = guild_row.skip_emoji();
     let guild_mode: &str = guild_mode.into();
+    let fallback_modes = if guild_row.fallback_modes.is_empty() {
+        Cow::Borrowed(none_str)
+    } else {
+        let modes: Vec<&str> = guild_row.fallback_modes.iter().map(Into::into).collect();
+        Cow::Owned(modes.join(", "))
+    };
     let to_translate = guild_row.to_translate();
     let require_voice = guild_row.require_voice();
     let text_in_voice = guild_row.text_in_voice();
@@ -153,7 +168,7 @@ pub async fn settings(ctx: Context<'_>) -> CommandResult {
         None => "Disabled",
     };
 
-    ctx.send(poise::CreateReply::default().embed(CreateEmbed::default()
+    let mut reply = poise::CreateReply::default().embed(CreateEmbed::default()
         .title("Current Settings")
         .colour(neutral_colour)
         .url(data.config.main_server_invite.as_str())
@@ -176,6 +191,7 @@ pub async fn settings(ctx: Context<'_>) -> CommandResult {
 
 **{sep2} Default Server Voice Mode: `{guild_mode}`**
 **{sep2} Default Server Voice: `{default_voice}`**
+{sep2} Fallback Voice Modes: `{fallback_modes}`
 
 {sep2} Max Time to Read: `{msg_length} seconds`
 {sep2} Max Repeated Characters: `{repeated_chars}`
@@ -192,7 +208,13 @@ pub async fn settings(ctx: Context<'_>) -> CommandResult {
 {sep3} Speaking Rate: `{speaking_rate}{speaking_rate_kind}`
         "),
         false)
-    )).await?;
+    );
+
+    for embed in mode_disabled_embeds(&data, is_premium) {
+        reply = reply.embed(embed);
+    }
+
+    ctx.send(reply).await?;
 
     Ok(())
 }
@@ -213,24 +235,25 @@ async fn voice_autocomplete<'a>(
         return Vec::new();
     };
 
-    let (mut i1, mut i2, mut i3, mut i4);
+    let voice_lists = data.voices();
+    let (mut i1, mut i2, mut i3, mut i4, mut i5, mut i6);
     let voices: &mut dyn Iterator<Item = _> = match mode {
         TTSMode::gTTS => {
-            i1 = data
+            i1 = voice_lists
                 .gtts_voices
                 .iter()
                 .map(|(k, v)| (v.to_string(), k.to_string()));
             &mut i1
         }
         TTSMode::eSpeak => {
-            i2 = data
+            i2 = voice_lists
                 .espeak_voices
                 .iter()
                 .map(|voice| (voice.to_string(), voice.to_string()));
             &mut i2
         }
         TTSMode::Polly => {
-            i3 = data.polly_voices.values().map(|voice| {
+            i3 = voice_lists.polly_voices.values().map(|voice| {
                 let name = format!(
                     "{} - {} ({})",
                     voice.name, voice.language_name, voice.gender
@@ -241,16 +264,32 @@ async fn voice_autocomplete<'a>(
             &mut i3
         }
         TTSMode::gCloud => {
-            i4 = data.gcloud_voices.iter().flat_map(|(language, variants)| {
-                variants.iter().map(move |(variant, gender)| {
-                    (
-                        format!("{language} {variant} ({gender})"),
-                        format!("{language} {variant}"),
-                    )
-                })
-            });
+            i4 = voice_lists
+                .gcloud_voices
+                .iter()
+                .flat_map(|(language, variants)| {
+                    variants.iter().map(move |(variant, gender)| {
+                        (
+                            format!("{language} {variant} ({gender})"),
+                            format!("{language} {variant}"),
+                        )
+                    })
+                });
             &mut i4
         }
+        TTSMode::OpenAI => {
+            i5 = voice_lists
+                .openai_voices
+                .iter()
+                .map(|voice| (voice.to_string(), voice.to_string()));
+            &mut i5
+        }
+        TTSMode::Dynamic(index) => {
+            i6 = voice_lists.dynamic_voices[usize::from(index)]
+                .iter()
+                .map(|(k, v)| (v.to_string(), k.to_string()));
+            &mut i6
+        }
     };
 
     let searching_lower = searching.to_lowercase();
@@ -273,6 +312,7 @@ async fn translation_languages_autocomplete<'a>(
 ) -> impl Iterator<Item = serenity::AutocompleteChoice<'a>> {
     let mut filtered_languages = ctx
         .data()
+        .voices()
         .translation_languages
         .iter()
         .filter(|(_, name)| name.starts_with(searching))
@@ -308,7 +348,7 @@ async fn change_mode<'a, CacheKey, RowT>(
 ) -> Result<Option<Cow<'a, str>>, Error>
 where
     CacheKey: database::CacheKeyTrait + Default + Send + Sync + Copy,
-    RowT: for<'r> sqlx::FromRow<'r, sqlx::postgres::PgRow> + Compact + Send + Sync + Unpin,
+    RowT: for<'r> sqlx::FromRow<'r, database::DbRow> + Compact + Send + Sync + Unpin,
 {
     let data = ctx.data();
     if let Some(mode) = mode
@@ -360,8 +400,8 @@ async fn change_voice<'a, T, RowT1, RowT2>(
     target: Target,
 ) -> Result<Cow<'a, str>, Error>
 where
-    RowT1: for<'r> sqlx::FromRow<'r, sqlx::postgres::PgRow> + Compact + Send + Sync + Unpin,
-    RowT2: for<'r> sqlx::FromRow<'r, sqlx::postgres::PgRow> + Compact + Send + Sync + Unpin,
+    RowT1: for<'r> sqlx::FromRow<'r, database::DbRow> + Compact + Send + Sync + Unpin,
+    RowT2: for<'r> sqlx::FromRow<'r, database::DbRow> + Compact + Send + Sync + Unpin,
 
     T: database::CacheKeyTrait + Default + Send + Sync + Copy,
     (T, TTSMode): database::CacheKeyTrait,
@@ -370,14 +410,15 @@ where
     let (_, mode) = data
         .parse_user_or_guild(ctx.http(), author_id, Some(guild_id))
         .await?;
+    let voices = data.voices();
     Ok(if let Some(voice) = voice {
-        if check_valid_voice(&data, &voice, mode) {
+        if check_valid_voice(&voices, &voice, mode) {
             general_db.create_row(key).await?;
             voice_db
                 .set_one((key, mode), "voice", voice.as_str())
                 .await?;
 
-            let name = get_voice_name(&data, &voice, mode).unwrap_or(&voice);
+            let name = get_voice_name(&voices, &voice, mode).unwrap_or(&voice);
             Cow::Owned(match target {
                 Target::Guild => format!("Changed the server voice to: {name}"),
                 Target::User => format!("Changed your voice to {name}"),
@@ -410,21 +451,31 @@ fn format_languages<'a>(mut iter: impl Iterator<Item = &'a FixedString<u8>>) ->
     buf
 }
 
-fn get_voice_name<'a>(data: &'a Data, code: &str, mode: TTSMode) -> Option<&'a FixedString<u8>> {
+fn get_voice_name<'a>(
+    voices: &'a VoiceLists,
+    code: &str,
+    mode: TTSMode,
+) -> Option<&'a FixedString<u8>> {
     match mode {
-        TTSMode::gTTS => data.gtts_voices.get(code),
-        TTSMode::Polly => data.polly_voices.get(code).map(|n| &n.name),
-        TTSMode::eSpeak | TTSMode::gCloud => None,
+        TTSMode::gTTS => voices.gtts_voices.get(code),
+        TTSMode::Polly => voices.polly_voices.get(code).map(|n| &n.name),
+        TTSMode::Dynamic(index) => voices.dynamic_voices[usize::from(index)].get(code),
+        TTSMode::eSpeak | TTSMode::gCloud | TTSMode::OpenAI => None,
     }
 }
 
-fn check_valid_voice(data: &Data, code: &FixedString<u8>, mode: TTSMode) -> bool {
+fn check_valid_voice(voices: &VoiceLists, code: &FixedString<u8>, mode: TTSMode) -> bool {
     match mode {
-        TTSMode::gTTS | TTSMode::Polly => get_voice_name(data, code, mode).is_some(),
-        TTSMode::eSpeak => data.espeak_voices.contains(code),
+        TTSMode::gTTS | TTSMode::Polly | TTSMode::Dynamic(_) => {
+            get_voice_name(voices, code, mode).is_some()
+        }
+        TTSMode::eSpeak => voices.espeak_voices.contains(code),
+        TTSMode::OpenAI => voices.openai_voices.contains(code),
         TTSMode::gCloud => code
             .split_once(' ')
-            .and_then(|(language, variant)| data.gcloud_voices.get(language).map(|l| (l, variant)))
+            .and_then(|(language, variant)| {
+                voices.gcloud_voices.get(language).map(|l| (l, variant))
+            })
             .is_some_and(|(ls, v)| ls.contains_key(v)),
     }
 }
@@ -444,6 +495,7 @@ fn check_prefix(prefix: &str) -> Result<ArrayString<5>, &'static str> {
     slash_command,
     required_bot_permissions = "SEND_MESSAGES | EMBED_LINKS"
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn set(ctx: Context<'_>) -> CommandResult {
     super::help::command(ctx, Some("set")).await
 }
@@ -482,6 +534,7 @@ macro_rules! create_bool_command {
         pub fn $name() -> Command {
             const RESPONSE: &str = concat!("The setting `", $key, "` is now {}.");
             #[poise::command(prefix_command)]
+            #[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id(), setting = $key))]
             pub async fn prefix_bool(ctx: Context<'_>, value: Option<bool>) -> CommandResult {
                 generic_bool_command(ctx, $key, value, RESPONSE).await
             }
@@ -495,6 +548,7 @@ macro_rules! create_bool_command {
                 required_bot_permissions="SEND_MESSAGES",
                 $($extra)*
             )]
+            #[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id(), setting = $key))]
             pub async fn slash_bool(ctx: Context<'_>, #[description = "True or False?"] value: bool) -> CommandResult {
                 generic_bool_command(ctx, $key, Some(value), RESPONSE).await
             }
@@ -564,6 +618,7 @@ create_bool_command!(
     slash_command,
     required_bot_permissions = "SEND_MESSAGES"
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 async fn use_new_formatting(
     ctx: Context<'_>,
     #[description = "Whether to use the experimental new message formatting"] value: bool,
@@ -588,6 +643,7 @@ async fn use_new_formatting(
     required_bot_permissions = "SEND_MESSAGES",
     aliases("required_role", "require_role")
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn required_role(
     ctx: Context<'_>,
     #[description = "The required role for all bot usage"] required_role: Option<serenity::Role>,
@@ -685,6 +741,7 @@ pub async fn required_role(
     required_bot_permissions = "SEND_MESSAGES",
     aliases("required_role", "require_role")
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 async fn required_prefix(
     ctx: Context<'_>,
     #[description = "The required prefix for TTS"] tts_prefix: Option<String>,
@@ -727,16 +784,18 @@ async fn required_prefix(
     required_bot_permissions = "SEND_MESSAGES | EMBED_LINKS",
     aliases("server_voice_mode", "server_tts_mode", "server_ttsmode")
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn server_mode(
     ctx: Context<'_>,
-    #[description = "The TTS Mode to change to"] mode: TTSModeChoice,
+    #[description = "The TTS Mode to change to"]
+    #[autocomplete = "mode_autocomplete"]
+    mode: TTSMode,
 ) -> CommandResult {
     let data = ctx.data();
     let guild_id = ctx.guild_id().unwrap();
 
-    let mode = TTSMode::from(mode);
-    if data.config.gtts_disabled.load(Ordering::Relaxed) && mode == TTSMode::gTTS {
-        ctx.send_error(GTTS_DISABLED_ERROR).await?;
+    if let Some(error) = mode_disabled_error(&data, mode) {
+        ctx.send_error(error).await?;
         return Ok(());
     }
 
@@ -756,6 +815,75 @@ pub async fn server_mode(
     Ok(())
 }
 
+/// Changes the modes messages are read in if the current mode fails, in order
+#[poise::command(
+    guild_only,
+    category = "Settings",
+    prefix_command,
+    slash_command,
+    required_permissions = "ADMINISTRATOR",
+    required_bot_permissions = "SEND_MESSAGES",
+    aliases("fallback_mode", "fallbacks")
+)]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
+pub async fn fallback_modes(
+    ctx: Context<'_>,
+    #[description = "The first TTS Mode to fall back to"]
+    #[autocomplete = "mode_autocomplete"]
+    first: Option<TTSMode>,
+    #[description = "The TTS Mode to fall back to if the first also fails"]
+    #[autocomplete = "mode_autocomplete"]
+    second: Option<TTSMode>,
+    #[description = "The TTS Mode to fall back to if the second also fails"]
+    #[autocomplete = "mode_autocomplete"]
+    third: Option<TTSMode>,
+) -> CommandResult {
+    let data = ctx.data();
+    let guild_id = ctx.guild_id().unwrap();
+
+    let mut modes: Vec<TTSMode> = Vec::with_capacity(3);
+    for mode in [first, second, third].into_iter().flatten() {
+        if !modes.contains(&mode) {
+            modes.push(mode);
+        }
+    }
+
+    if let Some(error) = modes
+        .iter()
+        .find_map(|mode| mode_disabled_error(&data, *mode))
+    {
+        ctx.send_error(error).await?;
+        return Ok(());
+    }
+
+    if modes.iter().any(|mode| mode.is_premium())
+        && !data.is_premium_simple(ctx.http(), guild_id).await?
+    {
+        ctx.send_error(
+            "Premium TTS Modes can only be used as fallbacks by TTS Bot Premium subscribers",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    data.guilds_db
+        .set_one(guild_id.into(), "fallback_modes", ModeList(modes.clone()))
+        .await?;
+
+    let msg = if modes.is_empty() {
+        Cow::Borrowed("Reset the server fallback modes")
+    } else {
+        let modes: Vec<_> = modes.iter().map(ToString::to_string).collect();
+        Cow::Owned(format!(
+            "Changed the server fallback modes to: {}",
+            modes.join(", ")
+        ))
+    };
+
+    ctx.say(msg).await?;
+    Ok(())
+}
+
 /// Changes the default language messages are read in
 #[poise::command(
     guild_only,
@@ -772,6 +900,7 @@ pub async fn server_mode(
         "serverlanguage"
     )
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn server_voice(
     ctx: Context<'_>,
     #[description = "The default voice to read messages in"]
@@ -809,6 +938,7 @@ pub async fn server_voice(
     required_bot_permissions = "SEND_MESSAGES | EMBED_LINKS",
     aliases("tlang", "tvoice", "target_lang", "target_voice", "target_language")
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn translation_lang(
     ctx: Context<'_>,
     #[description = "The language to translate all TTS messages to"]
@@ -819,7 +949,8 @@ pub async fn translation_lang(
     let guild_id = ctx.guild_id().unwrap().into();
 
     let to_say = if target_lang.as_ref().is_none_or(|target_lang| {
-        data.translation_languages
+        data.voices()
+            .translation_languages
             .contains_key(target_lang.as_str())
     }) {
         data.guilds_db
@@ -851,6 +982,7 @@ pub async fn translation_lang(
     required_permissions = "ADMINISTRATOR",
     required_bot_permissions = "SEND_MESSAGES"
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn command_prefix(
     ctx: Context<'_>,
     #[description = "The prefix to be used before commands"]
@@ -883,6 +1015,7 @@ pub async fn command_prefix(
     required_bot_permissions = "SEND_MESSAGES",
     aliases("repeated_chars", "repeated_letters", "chars")
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn repeated_characters(
     ctx: Context<'_>,
     #[description = "The max repeated characters"] chars: u8,
@@ -915,6 +1048,7 @@ pub async fn repeated_characters(
     required_bot_permissions = "SEND_MESSAGES",
     aliases("max_length", "message_length")
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn msg_length(
     ctx: Context<'_>,
     #[description = "Max length of TTS message in seconds"] seconds: u8,
@@ -954,6 +1088,7 @@ pub async fn msg_length(
         "tts_speed"
     )
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn speaking_rate(
     ctx: Context<'_>,
     #[description = "The speed to speak at"]
@@ -967,7 +1102,7 @@ pub async fn speaking_rate(
     let (_, mode) = data
         .parse_user_or_guild(ctx.http(), author.id, ctx.guild_id())
         .await?;
-    let Some(speaking_rate_info) = mode.speaking_rate_info() else {
+    let Some(speaking_rate_info) = data.speaking_rate_info(mode) else {
         let msg = aformat!("**Error**: Cannot set speaking rate for the {mode} mode");
         ctx.say(&*msg).await?;
         return Ok(());
@@ -1005,6 +1140,7 @@ pub async fn speaking_rate(
     required_bot_permissions = "SEND_MESSAGES",
     aliases("nick_name", "nickname", "name")
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn nick(
     ctx: Context<'_>,
     #[description = "The user to set the nick for, defaults to you"] user: Option<serenity::User>,
@@ -1066,19 +1202,21 @@ pub async fn nick(
     required_bot_permissions = "SEND_MESSAGES | EMBED_LINKS",
     aliases("voice_mode", "tts_mode", "ttsmode")
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn mode(
     ctx: Context<'_>,
-    #[description = "The TTS Mode to change to, leave blank for server default"] mode: Option<
-        TTSModeChoice,
-    >,
+    #[description = "The TTS Mode to change to, leave blank for server default"]
+    #[autocomplete = "mode_autocomplete"]
+    mode: Option<TTSMode>,
 ) -> CommandResult {
     let data = ctx.data();
     let author_id = ctx.author().id.into();
     let guild_id = ctx.guild_id().unwrap();
 
-    let mode = mode.map(TTSMode::from);
-    if data.config.gtts_disabled.load(Ordering::Relaxed) && mode == Some(TTSMode::gTTS) {
-        ctx.send_error(GTTS_DISABLED_ERROR).await?;
+    if let Some(mode) = mode
+        && let Some(error) = mode_disabled_error(&data, mode)
+    {
+        ctx.send_error(error).await?;
         return Ok(());
     }
 
@@ -1107,6 +1245,7 @@ pub async fn mode(
     slash_command,
     required_bot_permissions = "SEND_MESSAGES"
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn voice(
     ctx: Context<'_>,
     #[description = "The voice to read messages in, leave blank to reset"]
@@ -1142,6 +1281,7 @@ pub async fn voice(
     aliases("trans_langs", "translation_langs"),
     required_bot_permissions = "SEND_MESSAGES | EMBED_LINKS"
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn translation_languages(ctx: Context<'_>) -> CommandResult {
     let data = ctx.data();
     let author = ctx.author();
@@ -1162,7 +1302,7 @@ pub async fn translation_languages(ctx: Context<'_>) -> CommandResult {
                 .colour(neutral_colour)
                 .field(
                     "Currently Supported Languages",
-                    format_languages(data.translation_languages.keys()),
+                    format_languages(data.voices().translation_languages.keys()),
                     false,
                 )
                 .author(CreateEmbedAuthor::new(&*author.name).icon_url(author.face()))
@@ -1185,11 +1325,12 @@ pub async fn translation_languages(ctx: Context<'_>) -> CommandResult {
     slash_command,
     required_bot_permissions = "SEND_MESSAGES | EMBED_LINKS"
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn voices(
     ctx: Context<'_>,
-    #[description = "The mode to see the voices for, leave blank for current"] mode: Option<
-        TTSModeChoice,
-    >,
+    #[description = "The mode to see the voices for, leave blank for current"]
+    #[autocomplete = "mode_autocomplete"]
+    mode: Option<TTSMode>,
 ) -> CommandResult {
     let data = ctx.data();
     let http = ctx.http();
@@ -1198,7 +1339,7 @@ pub async fn voices(
     let guild_id = ctx.guild_id();
 
     let mode = match mode {
-        Some(mode) => TTSMode::from(mode),
+        Some(mode) => mode,
         None => data.parse_user_or_guild(http, author.id, guild_id).await?.1,
     };
 
@@ -1212,8 +1353,12 @@ pub async fn voices(
         };
 
         match mode {
-            TTSMode::gTTS => format_languages(data.gtts_voices.keys()),
-            TTSMode::eSpeak => format_languages(data.espeak_voices.iter()),
+            TTSMode::gTTS => format_languages(data.voices().gtts_voices.keys()),
+            TTSMode::eSpeak => format_languages(data.voices().espeak_voices.iter()),
+            TTSMode::OpenAI => format_languages(data.voices().openai_voices.iter()),
+            TTSMode::Dynamic(index) => {
+                format_languages(data.voices().dynamic_voices[usize::from(index)].keys())
+            }
             TTSMode::Polly => {
                 let (current_voice, pages) = list_polly_voices(&ctx).await?;
                 return run_paginator(current_voice, pages).await;
@@ -1262,16 +1407,19 @@ async fn list_polly_voices(ctx: &Context<'_>) -> Result<(String, Vec<String>)> {
     let (voice_id, mode) = data
         .parse_user_or_guild(ctx.http(), ctx.author().id, ctx.guild_id())
         .await?;
+    let voices = data.voices();
+    let default_voice = || voices.polly_voices.get(TTSMode::Polly.default_voice());
     let voice = match mode {
         TTSMode::Polly => {
             let voice_id: &str = &voice_id;
-            &data.polly_voices[voice_id]
+            voices.polly_voices.get(voice_id).or_else(default_voice)
         }
-        _ => &data.polly_voices[TTSMode::Polly.default_voice()],
-    };
+        _ => default_voice(),
+    }
+    .try_unwrap()?;
 
     let mut lang_to_voices: HashMap<_, Vec<_>> = HashMap::new();
-    for voice in data.polly_voices.values() {
+    for voice in voices.polly_voices.values() {
         lang_to_voices
             .entry(&voice.language_name)
             .or_default()
@@ -1313,7 +1461,8 @@ async fn list_gcloud_voices(ctx: &Context<'_>) -> Result<(String, Vec<String>)>
     .split_once(' ')
     .unwrap();
 
-    let pages = data
+    let voices = data.voices();
+    let pages = voices
         .gcloud_voices
         .iter()
         .map(|(language, variants)| {
@@ -1326,7 +1475,13 @@ async fn list_gcloud_voices(ctx: &Context<'_>) -> Result<(String, Vec<String>)>
         })
         .collect::<Result<_>>()?;
 
-    let gender = data.gcloud_voices[lang][variant];
+    let gender = voices
+        .gcloud_voices
+        .get(lang)
+        .and_then(|variants| variants.get(variant))
+        .copied()
+        .unwrap_or_default();
+
     Ok((format!("{lang} {variant} ({gender})"), pages))
 }
 
@@ -1349,6 +1504,7 @@ pub fn commands() -> [Command; 5] {
                 server_voice(),
                 mode(),
                 server_mode(),
+                fallback_modes(),
                 msg_length(),
                 botignore(),
                 translation(),
@@ -1364,7 +1520,8 @@ pub fn commands() -> [Command; 5] {
                 skip_emoji(),
                 owner::block(),
                 owner::bot_ban(),
-                owner::gtts_disabled(),
+                owner::disable_mode(),
+                owner::enable_mode(),
                 use_new_formatting(),
             ],
             ..set()
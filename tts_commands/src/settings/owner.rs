@@ -1,9 +1,12 @@
-use std::sync::atomic::Ordering;
+use std::borrow::Cow;
 
 use aformat::aformat;
-use poise::serenity_prelude as serenity;
+use poise::serenity_prelude::{self as serenity, small_fixed_array::FixedString};
 
-use tts_core::structs::{CommandResult, Context};
+use tts_core::{
+    kill_switches,
+    structs::{CommandResult, Context, TTSMode},
+};
 
 /// Owner only: used to block a user from dms
 #[poise::command(
@@ -13,6 +16,7 @@ use tts_core::structs::{CommandResult, Context};
     hide_in_help,
     required_bot_permissions = "SEND_MESSAGES"
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn block(ctx: Context<'_>, user: serenity::UserId, value: bool) -> CommandResult {
     ctx.data()
         .userinfo_db
@@ -31,6 +35,7 @@ pub async fn block(ctx: Context<'_>, user: serenity::UserId, value: bool) -> Com
     hide_in_help,
     required_bot_permissions = "SEND_MESSAGES"
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 pub async fn bot_ban(ctx: Context<'_>, user: serenity::UserId, value: bool) -> CommandResult {
     let user_id = user.into();
     let userinfo_db = &ctx.data().userinfo_db;
@@ -46,7 +51,7 @@ pub async fn bot_ban(ctx: Context<'_>, user: serenity::UserId, value: bool) -> C
     Ok(())
 }
 
-/// Owner only: Enables or disables the gTTS voice mode
+/// Owner only: Globally disables a voice mode, using the fallback mode instead
 #[poise::command(
     prefix_command,
     category = "Settings",
@@ -54,19 +59,64 @@ pub async fn bot_ban(ctx: Context<'_>, user: serenity::UserId, value: bool) -> C
     hide_in_help,
     required_bot_permissions = "SEND_MESSAGES"
 )]
-pub async fn gtts_disabled(ctx: Context<'_>, value: bool) -> CommandResult {
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
+pub async fn disable_mode(
+    ctx: Context<'_>,
+    mode: TTSMode,
+    fallback: TTSMode,
+    #[rest] reason: FixedString<u16>,
+) -> CommandResult {
     let data = ctx.data();
-    if data.config.gtts_disabled.swap(value, Ordering::Relaxed) == value {
-        ctx.say("It's already set that way, silly.").await?;
-        return Ok(());
-    }
 
-    let msg = if value {
-        "Disabled gTTS globally, womp womp"
+    let error = if mode == fallback {
+        Some(Cow::Borrowed("A mode cannot fall back to itself."))
+    } else if !mode.is_premium() && fallback.is_premium() {
+        Some(Cow::Borrowed(
+            "A free mode cannot fall back to a premium mode.",
+        ))
+    } else if data.mode_kill_switches.contains_key(&fallback) {
+        Some(Cow::Owned(format!("`{fallback}` is also disabled.")))
+    } else if let Some(kill_switch) = data
+        .mode_kill_switches
+        .iter()
+        .find(|kill_switch| kill_switch.fallback == mode)
+    {
+        let dependant = kill_switch.key();
+        Some(Cow::Owned(format!(
+            "`{mode}` is the fallback for `{dependant}`."
+        )))
     } else {
-        "Re-enabled gTTS globally, yippee!\nMake sure to check the config file though."
+        None
     };
 
-    ctx.say(msg).await?;
+    if let Some(error) = error {
+        ctx.say(error).await?;
+        return Ok(());
+    }
+
+    kill_switches::disable_mode(data, mode, fallback, reason).await?;
+
+    let msg = aformat!("Disabled `{mode}` globally, falling back to `{fallback}`.");
+    ctx.say(msg.as_str()).await?;
+    Ok(())
+}
+
+/// Owner only: Re-enables a globally disabled voice mode
+#[poise::command(
+    prefix_command,
+    category = "Settings",
+    owners_only,
+    hide_in_help,
+    required_bot_permissions = "SEND_MESSAGES"
+)]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
+pub async fn enable_mode(ctx: Context<'_>, mode: TTSMode) -> CommandResult {
+    if !kill_switches::enable_mode(ctx.data(), mode).await? {
+        ctx.say("It's already set that way, silly.").await?;
+        return Ok(());
+    }
+
+    let msg = aformat!("Re-enabled `{mode}` globally, yippee!");
+    ctx.say(msg.as_str()).await?;
     Ok(())
 }
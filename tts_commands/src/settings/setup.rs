@@ -159,6 +159,7 @@ fn generate_channel_select(text_channels: &[EligibleSetupChannel]) -> Vec<Create
     required_permissions = "ADMINISTRATOR",
     required_bot_permissions = "SEND_MESSAGES | EMBED_LINKS"
 )]
+#[tracing::instrument(skip_all, fields(guild_id = ?ctx.guild_id()))]
 
 pub async fn setup(
     ctx: Context<'_>,
@@ -4,18 +4,18 @@ use dashmap::DashMap;
 
 use serenity::futures;
 
-use crate::{bool_enum, structs::Context};
+use crate::{bool_enum, database, structs::Context};
 
 bool_enum!(EventType(Normal | Command));
 
 pub struct Handler {
     pub log_buffer: DashMap<(Cow<'static, str>, EventType), i32>,
-    pub pool: sqlx::PgPool,
+    pub pool: database::Pool,
 }
 
 impl Handler {
     #[must_use]
-    pub fn new(pool: sqlx::PgPool) -> Self {
+    pub fn new(pool: database::Pool) -> Self {
         Self {
             pool,
             log_buffer: DashMap::new(),
@@ -0,0 +1,213 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use parking_lot::{Mutex, RwLock};
+use poise::serenity_prelude::small_fixed_array::FixedString;
+use rand::Rng as _;
+use serenity::futures::{stream::FuturesUnordered, StreamExt as _};
+
+use crate::structs::TTSServiceConfig;
+
+/// How many failures in a row before an endpoint stops receiving requests.
+const FAILURE_THRESHOLD: u32 = 5;
+/// How long an endpoint is skipped for once its circuit breaker has opened.
+const OPEN_DURATION: Duration = Duration::from_secs(30);
+
+#[derive(Default)]
+struct BreakerState {
+    failures: u32,
+    open_until: Option<Instant>,
+    /// If the breaker has opened since the last success, even if it lets requests through again.
+    tripped: bool,
+}
+
+/// Stops sending requests to an endpoint after repeated failures, then lets them through again
+/// after [`OPEN_DURATION`], where a single failure will open the breaker again.
+#[derive(Default)]
+pub struct CircuitBreaker(Mutex<BreakerState>);
+
+impl CircuitBreaker {
+    pub fn is_open(&self) -> bool {
+        self.0
+            .lock()
+            .open_until
+            .is_some_and(|open_until| open_until > Instant::now())
+    }
+
+    /// Closes the breaker, returning if it had tripped.
+    pub fn record_success(&self) -> bool {
+        std::mem::take(&mut *self.0.lock()).tripped
+    }
+
+    /// Counts a failure, returning if this tripped the breaker.
+    pub fn record_failure(&self) -> bool {
+        let mut state = self.0.lock();
+        state.failures += 1;
+
+        if state.failures < FAILURE_THRESHOLD {
+            return false;
+        }
+
+        state.open_until = Some(Instant::now() + OPEN_DURATION);
+        !std::mem::replace(&mut state.tripped, true)
+    }
+}
+
+pub struct Endpoint {
+    pub url: reqwest::Url,
+    pub weight: u32,
+    pub breaker: CircuitBreaker,
+}
+
+impl Endpoint {
+    /// Records the outcome of a request, logging when the circuit breaker opens or closes.
+    pub fn record(&self, success: bool) {
+        if success {
+            if self.breaker.record_success() {
+                tracing::info!("tts-service at {} has recovered", self.url);
+            }
+        } else if self.breaker.record_failure() {
+            tracing::warn!(
+                "tts-service at {} is failing, skipping it for now",
+                self.url
+            );
+        }
+    }
+}
+
+/// Every configured tts-service instance, routed between by weight.
+///
+/// The instances and auth key can be swapped out by a config reload, see [`Self::reload`].
+pub struct Endpoints {
+    reqwest: reqwest::Client,
+    endpoints: RwLock<Vec<Arc<Endpoint>>>,
+    auth_key: RwLock<Option<FixedString>>,
+}
+
+impl Endpoints {
+    #[must_use]
+    pub fn new(
+        reqwest: reqwest::Client,
+        configs: Vec<TTSServiceConfig>,
+        auth_key: Option<FixedString>,
+    ) -> Self {
+        let endpoints = Self {
+            reqwest,
+            endpoints: RwLock::default(),
+            auth_key: RwLock::default(),
+        };
+
+        endpoints.reload(configs, auth_key);
+        endpoints
+    }
+
+    /// Replaces the instances and auth key, keeping the circuit breaker of unchanged instances.
+    pub fn reload(&self, configs: Vec<TTSServiceConfig>, auth_key: Option<FixedString>) {
+        let old_endpoints = self.endpoints.read().clone();
+        let endpoints = configs
+            .into_iter()
+            .map(|config| {
+                let weight = config.weight.unwrap_or(1);
+                let existing = old_endpoints
+                    .iter()
+                    .find(|endpoint| endpoint.url == config.url && endpoint.weight == weight);
+
+                existing.cloned().unwrap_or_else(|| {
+                    Arc::new(Endpoint {
+                        url: config.url,
+                        weight,
+                        breaker: CircuitBreaker::default(),
+                    })
+                })
+            })
+            .collect();
+
+        *self.endpoints.write() = endpoints;
+        *self.auth_key.write() = auth_key;
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.endpoints.read().is_empty()
+    }
+
+    #[must_use]
+    pub fn auth_key(&self) -> Option<FixedString> {
+        self.auth_key.read().clone()
+    }
+
+    /// Returns the endpoints with a closed circuit breaker, in a random order weighted by
+    /// `weight`, so the first endpoint is tried first and the rest are used for failover.
+    pub fn route(&self) -> Vec<Arc<Endpoint>> {
+        let mut candidates: Vec<_> = self
+            .endpoints
+            .read()
+            .iter()
+            .filter(|endpoint| !endpoint.breaker.is_open())
+            .cloned()
+            .collect();
+
+        let mut rng = rand::thread_rng();
+        let mut ordered = Vec::with_capacity(candidates.len());
+        while !candidates.is_empty() {
+            let total_weight: u32 = candidates.iter().map(|endpoint| endpoint.weight).sum();
+            let mut pick = rng.gen_range(0..total_weight.max(1));
+
+            let index = candidates
+                .iter()
+                .position(|endpoint| {
+                    if pick < endpoint.weight {
+                        true
+                    } else {
+                        pick -= endpoint.weight;
+                        false
+                    }
+                })
+                .unwrap_or(0);
+
+            ordered.push(candidates.swap_remove(index));
+        }
+
+        ordered
+    }
+
+    /// Checks that at least one endpoint responds without a server error, for health checks.
+    ///
+    /// Every endpoint is requested at once, returning as soon as one has answered.
+    pub async fn is_reachable(&self) -> bool {
+        let endpoints = self.endpoints.read().clone();
+        let requests: FuturesUnordered<_> = endpoints
+            .iter()
+            .map(|endpoint| {
+                self.reqwest
+                    .get(endpoint.url.clone())
+                    .timeout(Duration::from_secs(5))
+                    .send()
+            })
+            .collect();
+
+        requests
+            .any(|resp| std::future::ready(resp.is_ok_and(|resp| !resp.status().is_server_error())))
+            .await
+    }
+
+    /// Sends a request to every endpoint, so broken endpoints are found before users notice
+    /// and recovered endpoints are used again.
+    pub async fn probe(&self) {
+        let endpoints = self.endpoints.read().clone();
+        let probes = endpoints.iter().map(|endpoint| async move {
+            let resp = self
+                .reqwest
+                .get(endpoint.url.clone())
+                .timeout(Duration::from_secs(5))
+                .send()
+                .await;
+
+            endpoint.record(resp.is_ok_and(|resp| !resp.status().is_server_error()));
+        });
+
+        serenity::futures::future::join_all(probes).await;
+    }
+}
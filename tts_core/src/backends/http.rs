@@ -0,0 +1,198 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Context as _;
+use rand::Rng as _;
+
+use super::{
+    endpoints::Endpoints, unless_format_error, Audio, AudioData, SynthesisRequest, TtsBackend,
+};
+use crate::{
+    common::{check_audio_response, prepare_url},
+    structs::{Result, TTSMode, TTSServiceError},
+};
+
+/// The original backend, sending every mode to the configured tts-service instances.
+pub struct HttpBackend {
+    reqwest: reqwest::Client,
+    endpoints: Arc<Endpoints>,
+    max_retries: u32,
+}
+
+/// Doubles the delay for each retry, adding up to the same again in jitter so retries from
+/// many messages do not hit the tts-service at the same time.
+fn retry_backoff(attempt: u32) -> Duration {
+    let base_millis = 100 * 2_u64.pow(attempt.saturating_sub(1).min(6));
+    let jitter_millis = rand::thread_rng().gen_range(0..=base_millis);
+
+    Duration::from_millis(base_millis + jitter_millis)
+}
+
+impl HttpBackend {
+    #[must_use]
+    pub fn new(reqwest: reqwest::Client, endpoints: Arc<Endpoints>, max_retries: u32) -> Self {
+        Self {
+            reqwest,
+            endpoints,
+            max_retries,
+        }
+    }
+
+    /// Sends a request to each healthy endpoint in turn, until one responds without a server error.
+    ///
+    /// If every endpoint fails, this is retried up to `max_retries` times with a backoff.
+    ///
+    /// A server error from the tts-service itself means the mode's provider failed, which is
+    /// returned straight away and left to `ModeHealth`, as the other endpoints use it too.
+    async fn send(
+        &self,
+        build_url: impl Fn(reqwest::Url) -> reqwest::Url + Send,
+    ) -> Result<reqwest::Response> {
+        let auth_key = self.endpoints.auth_key();
+        let auth_key = auth_key.as_deref().unwrap_or("");
+
+        let mut last_failure = None;
+        for attempt in 0..=self.max_retries {
+            if attempt != 0 {
+                tokio::time::sleep(retry_backoff(attempt)).await;
+            }
+
+            for endpoint in self.endpoints.route() {
+                let resp = self
+                    .reqwest
+                    .get(build_url(endpoint.url.clone()))
+                    .header(reqwest::header::AUTHORIZATION, auth_key)
+                    .send()
+                    .await;
+
+                let resp = match resp {
+                    Ok(resp) => resp,
+                    Err(err) => {
+                        endpoint.record(false);
+                        last_failure = Some(err.into());
+                        continue;
+                    }
+                };
+
+                let status = resp.status();
+                if !status.is_server_error() {
+                    endpoint.record(true);
+                    return Ok(resp);
+                }
+
+                match resp.json::<TTSServiceError>().await {
+                    Ok(err) => {
+                        endpoint.record(true);
+                        return Err(err)
+                            .context("The tts-service failed to fetch from its provider");
+                    }
+                    Err(_) => {
+                        endpoint.record(false);
+                        last_failure = Some(anyhow::anyhow!("tts-service responded with {status}"));
+                    }
+                }
+            }
+        }
+
+        match last_failure {
+            Some(err) => Err(err),
+            None => anyhow::bail!("Every tts-service endpoint is currently unavailable"),
+        }
+    }
+}
+
+#[serenity::async_trait]
+impl TtsBackend for HttpBackend {
+    fn name(&self) -> &'static str {
+        "tts-service"
+    }
+
+    fn supports(&self, mode: TTSMode) -> bool {
+        mode != TTSMode::OpenAI
+    }
+
+    async fn fetch_modes(&self) -> Result<Option<serde_json::Value>> {
+        let resp = self
+            .send(|mut url| {
+                url.set_path("modes");
+                url
+            })
+            .await?;
+
+        // Older tts-service versions only support the built in modes.
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        Ok(Some(resp.error_for_status()?.json().await?))
+    }
+
+    async fn fetch_translation_languages(&self) -> Result<Option<serde_json::Value>> {
+        let resp = self
+            .send(|mut url| {
+                url.set_path("translation_languages");
+                url
+            })
+            .await?;
+
+        Ok(Some(resp.error_for_status()?.json().await?))
+    }
+
+    async fn fetch_voices(&self, mode: TTSMode) -> Result<serde_json::Value> {
+        let resp = self
+            .send(|mut url| {
+                url.set_path("voices");
+                url.query_pairs_mut()
+                    .append_pair("mode", mode.into())
+                    .append_pair("raw", "true")
+                    .finish();
+
+                url
+            })
+            .await?;
+
+        Ok(resp.error_for_status()?.json().await?)
+    }
+
+    async fn synthesise(&self, request: SynthesisRequest<'_>) -> Result<Option<Audio>> {
+        let send = |format| {
+            self.send(move |url| {
+                prepare_url(
+                    url,
+                    request.text,
+                    request.voice,
+                    request.mode,
+                    request.speaking_rate,
+                    request.max_length,
+                    request.translation_lang,
+                    format,
+                )
+            })
+        };
+
+        let format = request.mode.preferred_format();
+        let resp = match unless_format_error(format, send(format).await?).await? {
+            Some(resp) => resp,
+            None => {
+                tracing::debug!(
+                    "tts-service rejected {format} for {}, retrying as mp3",
+                    request.mode
+                );
+                send("mp3").await?
+            }
+        };
+
+        let Some(resp) = check_audio_response(resp).await? else {
+            return Ok(None);
+        };
+
+        let mime_type = resp
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .map(|ct| ct.to_str().map(String::from))
+            .transpose()?;
+
+        // The tts-service has already checked the request, so play the audio as it arrives.
+        let data = AudioData::Streaming(resp);
+        Ok(Some(Audio { data, mime_type }))
+    }
+}
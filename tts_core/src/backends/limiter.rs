@@ -0,0 +1,57 @@
+use std::{
+    num::NonZeroUsize,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// Decrements the queue depth when dropped, even if the request was cancelled while waiting.
+struct QueueGuard<'a>(&'a AtomicUsize);
+
+impl Drop for QueueGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Limits how many TTS requests are sent at once, so a traffic spike queues instead of
+/// overloading the backends.
+pub struct RequestLimiter {
+    semaphore: Semaphore,
+    max_concurrency: usize,
+    queued: AtomicUsize,
+}
+
+impl RequestLimiter {
+    pub const DEFAULT_MAX_CONCURRENCY: NonZeroUsize = NonZeroUsize::new(64).unwrap();
+
+    #[must_use]
+    pub fn new(max_concurrency: NonZeroUsize) -> Self {
+        let max_concurrency = max_concurrency.get();
+        Self {
+            max_concurrency,
+            semaphore: Semaphore::new(max_concurrency),
+            queued: AtomicUsize::new(0),
+        }
+    }
+
+    pub async fn acquire(&self) -> SemaphorePermit<'_> {
+        self.queued.fetch_add(1, Ordering::Relaxed);
+        let _guard = QueueGuard(&self.queued);
+
+        self.semaphore
+            .acquire()
+            .await
+            .expect("The semaphore is never closed")
+    }
+
+    /// How many requests are currently being sent.
+    pub fn in_flight(&self) -> usize {
+        self.max_concurrency - self.semaphore.available_permits()
+    }
+
+    /// How many requests are waiting to be sent.
+    pub fn queue_depth(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+}
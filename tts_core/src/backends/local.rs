@@ -0,0 +1,211 @@
+use std::{path::PathBuf, process::Stdio, time::Duration};
+
+use tokio::io::AsyncWriteExt as _;
+
+use super::{Audio, AudioData, SynthesisRequest, TtsBackend};
+use crate::structs::{LocalEngine, LocalTTSConfig, Result, TTSMode};
+
+/// The speaking rate that the eSpeak mode uses by default, in words per minute.
+const DEFAULT_WPM: f32 = 175.0;
+
+/// How long the engine may take to run, before it is killed.
+const RUN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The length of the RIFF and format chunks, before the audio data.
+const WAV_HEADER_LEN: usize = 44;
+
+/// The length of WAV audio in whole seconds, from the byte rate in its header.
+///
+/// The data size in the header is ignored, as engines writing to a pipe cannot fill it in.
+fn wav_duration_secs(wav: &[u8]) -> Option<u64> {
+    if !wav.starts_with(b"RIFF") {
+        return None;
+    }
+
+    let byte_rate = u32::from_le_bytes(wav.get(28..32)?.try_into().ok()?);
+    let data_len = u64::try_from(wav.len().checked_sub(WAV_HEADER_LEN)?).ok()?;
+    data_len.checked_div(u64::from(byte_rate))
+}
+
+/// Runs a TTS engine binary on the same machine, allowing the bot to run without the tts-service.
+///
+/// This only provides the eSpeak mode, as the other modes rely on external APIs.
+pub struct LocalBackend {
+    engine: LocalEngine,
+    binary: PathBuf,
+    voices_dir: Option<PathBuf>,
+    default_voice: Option<String>,
+}
+
+impl LocalBackend {
+    pub fn new(config: LocalTTSConfig) -> Result<Self> {
+        let binary = match (&config.binary, config.engine) {
+            (Some(binary), _) => PathBuf::from(binary.as_str()),
+            (None, LocalEngine::EspeakNg) => PathBuf::from("espeak-ng"),
+            (None, LocalEngine::Piper) => PathBuf::from("piper"),
+        };
+
+        let voices_dir = config.voices_dir.map(|dir| PathBuf::from(dir.as_str()));
+        if matches!(config.engine, LocalEngine::Piper) && voices_dir.is_none() {
+            anyhow::bail!("`voices_dir` must be set to use piper as a local TTS engine");
+        }
+
+        Ok(Self {
+            binary,
+            voices_dir,
+            engine: config.engine,
+            default_voice: config.default_voice.map(String::from),
+        })
+    }
+
+    async fn run(&self, args: &[String], stdin: &str) -> Result<Vec<u8>> {
+        let mut child = tokio::process::Command::new(&self.binary)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+
+        // Written from another task, as the engine may block on writing a full stdout pipe
+        // before it has read all of stdin.
+        let child_stdin = child.stdin.take();
+        let stdin = stdin.to_owned();
+        let writer = tokio::spawn(async move {
+            if let Some(mut child_stdin) = child_stdin {
+                child_stdin.write_all(stdin.as_bytes()).await?;
+            }
+
+            std::io::Result::Ok(())
+        });
+
+        // On timeout, the child is dropped with `wait_with_output`, which kills it.
+        let Ok(output) = tokio::time::timeout(RUN_TIMEOUT, child.wait_with_output()).await else {
+            writer.abort();
+            anyhow::bail!(
+                "{} took longer than {}s",
+                self.binary.display(),
+                RUN_TIMEOUT.as_secs()
+            );
+        };
+
+        let output = output?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!(
+                "{} exited with {}: {stderr}",
+                self.binary.display(),
+                output.status
+            );
+        }
+
+        writer.await??;
+        Ok(output.stdout)
+    }
+
+    async fn espeak_voices(&self) -> Result<Vec<String>> {
+        let output = self.run(&[String::from("--voices=mb")], "").await?;
+
+        // Skips the header, then takes the voice file name, such as `mb/mb-en1`.
+        let voices = String::from_utf8(output)?
+            .lines()
+            .skip(1)
+            .filter_map(|line| line.split_whitespace().nth(4))
+            .filter_map(|file| file.rsplit('/').next()?.strip_prefix("mb-"))
+            .map(String::from)
+            .collect();
+
+        Ok(voices)
+    }
+
+    async fn piper_voices(&self) -> Result<Vec<String>> {
+        let voices_dir = self.voices_dir.as_ref().unwrap();
+
+        let mut voices = Vec::new();
+        let mut entries = tokio::fs::read_dir(voices_dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "onnx")
+                && let Some(name) = path.file_stem().and_then(|name| name.to_str())
+            {
+                voices.push(String::from(name));
+            }
+        }
+
+        voices.sort_unstable();
+        Ok(voices)
+    }
+
+    fn piper_model(&self, voice: &str) -> Result<PathBuf> {
+        let voices_dir = self.voices_dir.as_ref().unwrap();
+
+        let model = voices_dir.join(format!("{voice}.onnx"));
+        if model.exists() {
+            return Ok(model);
+        }
+
+        match &self.default_voice {
+            Some(default_voice) => Ok(voices_dir.join(format!("{default_voice}.onnx"))),
+            None => anyhow::bail!("Unknown piper voice `{voice}` and no `default_voice` is set"),
+        }
+    }
+}
+
+#[serenity::async_trait]
+impl TtsBackend for LocalBackend {
+    fn name(&self) -> &'static str {
+        match self.engine {
+            LocalEngine::EspeakNg => "espeak-ng",
+            LocalEngine::Piper => "piper",
+        }
+    }
+
+    fn supports(&self, mode: TTSMode) -> bool {
+        mode == TTSMode::eSpeak
+    }
+
+    async fn fetch_voices(&self, _mode: TTSMode) -> Result<serde_json::Value> {
+        let voices = match self.engine {
+            LocalEngine::EspeakNg => self.espeak_voices().await?,
+            LocalEngine::Piper => self.piper_voices().await?,
+        };
+
+        Ok(serde_json::Value::from(voices))
+    }
+
+    async fn synthesise(&self, request: SynthesisRequest<'_>) -> Result<Option<Audio>> {
+        let words_per_minute = request.speaking_rate.parse().unwrap_or(DEFAULT_WPM);
+        let args = match self.engine {
+            LocalEngine::EspeakNg => vec![
+                String::from("--stdin"),
+                String::from("--stdout"),
+                format!("-vmb-{}", request.voice),
+                format!("-s{}", words_per_minute.round()),
+            ],
+            LocalEngine::Piper => vec![
+                String::from("--model"),
+                self.piper_model(request.voice)?
+                    .to_string_lossy()
+                    .into_owned(),
+                String::from("--length_scale"),
+                (DEFAULT_WPM / words_per_minute).to_string(),
+                String::from("--output_file"),
+                String::from("-"),
+            ],
+        };
+
+        let data = self.run(&args, request.text).await?;
+
+        // Matches the tts-service, which ignores messages over the guild's max length.
+        if let Ok(max_length) = request.max_length.parse::<u64>()
+            && wav_duration_secs(&data).is_some_and(|duration| duration > max_length)
+        {
+            return Ok(None);
+        }
+
+        Ok(Some(Audio {
+            data: AudioData::Buffered(data),
+            mime_type: Some(String::from("audio/wav")),
+        }))
+    }
+}
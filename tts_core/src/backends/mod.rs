@@ -0,0 +1,123 @@
+use crate::structs::{Result, SpeakingRateInfo, TTSMode};
+
+mod endpoints;
+mod http;
+mod limiter;
+mod local;
+mod mode_health;
+mod openai;
+mod stream;
+
+pub use endpoints::Endpoints;
+pub use http::HttpBackend;
+pub use limiter::RequestLimiter;
+pub use local::LocalBackend;
+pub use mode_health::ModeHealth;
+pub use openai::OpenAIBackend;
+pub use stream::ResponseStream;
+
+/// Everything needed to generate a single TTS message.
+pub struct SynthesisRequest<'a> {
+    pub text: &'a str,
+    pub voice: &'a str,
+    pub mode: TTSMode,
+    pub speaking_rate: &'a str,
+    /// The maximum length of the audio in seconds, backends may ignore this.
+    pub max_length: &'a str,
+    /// The language to translate the text to before speaking, backends may ignore this.
+    pub translation_lang: Option<&'a str>,
+}
+
+/// Returns the response, unless the backend rejected the audio format asked for.
+///
+/// Not every backend supports Ogg/Opus, so these requests are retried once as mp3. Other
+/// mistakes in a request, such as an unknown voice, are also a 400, so the error body must
+/// mention the format. Reading the body consumes the response, so it is rebuilt for the caller.
+async fn unless_format_error(
+    format: &str,
+    resp: reqwest::Response,
+) -> Result<Option<reqwest::Response>> {
+    use reqwest::StatusCode;
+
+    if format == "mp3" {
+        return Ok(Some(resp));
+    }
+
+    match resp.status() {
+        StatusCode::UNSUPPORTED_MEDIA_TYPE => return Ok(None),
+        StatusCode::BAD_REQUEST => {}
+        _ => return Ok(Some(resp)),
+    }
+
+    let mut rebuilt = http::Response::builder().status(resp.status());
+    if let Some(headers) = rebuilt.headers_mut() {
+        headers.clone_from(resp.headers());
+    }
+
+    let body = resp.bytes().await?;
+    let error = String::from_utf8_lossy(&body).to_lowercase();
+    if error.contains("format") || error.contains(format) {
+        return Ok(None);
+    }
+
+    Ok(Some(rebuilt.body(body)?.into()))
+}
+
+/// The body of generated audio, which may still be downloading.
+pub enum AudioData {
+    Buffered(Vec<u8>),
+    Streaming(reqwest::Response),
+}
+
+/// Generated audio, alongside the MIME type if known.
+pub struct Audio {
+    pub data: AudioData,
+    pub mime_type: Option<String>,
+}
+
+impl Audio {
+    /// Waits for the rest of the audio to download, for when it cannot be streamed.
+    pub async fn into_bytes(self) -> Result<Vec<u8>> {
+        match self.data {
+            AudioData::Buffered(data) => Ok(data),
+            AudioData::Streaming(resp) => Ok(resp.bytes().await?.to_vec()),
+        }
+    }
+
+    #[must_use]
+    pub fn file_extension(&self) -> &'static str {
+        match self.mime_type.as_deref() {
+            Some("audio/wav" | "audio/x-wav" | "audio/wave") => "wav",
+            Some("audio/ogg" | "audio/opus") => "ogg",
+            _ => "mp3",
+        }
+    }
+}
+
+/// A source of TTS audio, such as the HTTP tts-service or a local engine binary.
+#[serenity::async_trait]
+pub trait TtsBackend: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn supports(&self, mode: TTSMode) -> bool;
+
+    fn speaking_rate_info(&self, mode: TTSMode) -> Option<SpeakingRateInfo> {
+        mode.speaking_rate_info()
+    }
+
+    /// Fetches the raw list of extra modes this backend provides, to be registered as
+    /// [`TTSMode::Dynamic`], or `None` if it does not provide any.
+    async fn fetch_modes(&self) -> Result<Option<serde_json::Value>> {
+        Ok(None)
+    }
+
+    /// Fetches the raw list of translation languages, or `None` if translation is not supported.
+    async fn fetch_translation_languages(&self) -> Result<Option<serde_json::Value>> {
+        Ok(None)
+    }
+
+    /// Fetches the raw voice list for a mode, in the same format as the tts-service.
+    async fn fetch_voices(&self, mode: TTSMode) -> Result<serde_json::Value>;
+
+    /// Generates audio for a request, returning `None` if the audio should be silently skipped.
+    async fn synthesise(&self, request: SynthesisRequest<'_>) -> Result<Option<Audio>>;
+}
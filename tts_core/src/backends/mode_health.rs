@@ -0,0 +1,81 @@
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+
+use crate::structs::TTSMode;
+
+/// How long error rates are measured over before being reset.
+const WINDOW: Duration = Duration::from_secs(60);
+/// The minimum amount of requests in a window before a mode can be marked as failing.
+const MIN_REQUESTS: u32 = 10;
+/// The proportion of failed requests for a mode to be marked as failing.
+const FAILURE_RATE: f32 = 0.5;
+/// How long a failing mode is replaced by its fallback for.
+const FALLBACK_DURATION: Duration = Duration::from_secs(5 * 60);
+
+struct ModeStats {
+    window_start: Instant,
+    successes: u32,
+    failures: u32,
+    failing_until: Option<Instant>,
+}
+
+impl Default for ModeStats {
+    fn default() -> Self {
+        Self {
+            window_start: Instant::now(),
+            successes: 0,
+            failures: 0,
+            failing_until: None,
+        }
+    }
+}
+
+/// Tracks the error rate of each mode, so a broken mode can automatically be swapped out.
+#[derive(Default)]
+pub struct ModeHealth(DashMap<TTSMode, ModeStats>);
+
+impl ModeHealth {
+    pub fn record(&self, mode: TTSMode, success: bool) {
+        let mut stats = self.0.entry(mode).or_default();
+
+        let now = Instant::now();
+        if now.duration_since(stats.window_start) > WINDOW {
+            stats.window_start = now;
+            stats.successes = 0;
+            stats.failures = 0;
+        }
+
+        if success {
+            stats.successes += 1;
+            return;
+        }
+
+        stats.failures += 1;
+
+        let total = stats.successes + stats.failures;
+        let failure_rate = stats.failures as f32 / total as f32;
+        if total >= MIN_REQUESTS && failure_rate >= FAILURE_RATE && !stats.is_failing(now) {
+            tracing::warn!(
+                "{mode} has failed {}/{total} requests, falling back for {} minutes",
+                stats.failures,
+                FALLBACK_DURATION.as_secs() / 60
+            );
+
+            stats.failing_until = Some(now + FALLBACK_DURATION);
+        }
+    }
+
+    pub fn is_failing(&self, mode: TTSMode) -> bool {
+        self.0
+            .get(&mode)
+            .is_some_and(|stats| stats.is_failing(Instant::now()))
+    }
+}
+
+impl ModeStats {
+    fn is_failing(&self, now: Instant) -> bool {
+        self.failing_until
+            .is_some_and(|failing_until| failing_until > now)
+    }
+}
@@ -0,0 +1,158 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    OnceLock,
+};
+
+use poise::serenity_prelude::small_fixed_array::FixedString;
+
+use super::{unless_format_error, Audio, AudioData, SynthesisRequest, TtsBackend};
+use crate::structs::{OpenAIConfig, Result, TTSMode};
+
+/// Set once the backend has been created, from the `[OpenAI-TTS]` config.
+static DEFAULT_VOICE: OnceLock<&'static str> = OnceLock::new();
+
+#[derive(serde::Serialize)]
+struct SpeechRequest<'a> {
+    model: &'a str,
+    input: &'a str,
+    voice: &'a str,
+    speed: f32,
+    response_format: &'static str,
+}
+
+/// Calls any OpenAI compatible `/v1/audio/speech` endpoint, for the OpenAI mode.
+///
+/// Voices are stored as `{model} {voice}`, similar to how gCloud stores `{language} {variant}`.
+pub struct OpenAIBackend {
+    reqwest: reqwest::Client,
+    base_url: reqwest::Url,
+    api_key: Option<FixedString>,
+    models: Vec<FixedString>,
+    voices: Vec<FixedString>,
+    /// Set once the endpoint has rejected the preferred format, so later requests ask for mp3.
+    mp3_only: AtomicBool,
+}
+
+impl OpenAIBackend {
+    pub fn new(reqwest: reqwest::Client, config: OpenAIConfig) -> Result<Self> {
+        if config.models.is_empty() || config.voices.is_empty() {
+            anyhow::bail!("`models` and `voices` must not be empty to use OpenAI TTS");
+        }
+
+        let default_voice = format!("{} {}", config.models[0], config.voices[0]);
+        // This lives for the rest of the program, so `TTSMode::default_voice` can borrow it.
+        let _ = DEFAULT_VOICE.set(default_voice.leak());
+
+        Ok(Self {
+            reqwest,
+            base_url: config.base_url,
+            api_key: config.api_key,
+            models: config.models,
+            voices: config.voices,
+            mp3_only: AtomicBool::new(false),
+        })
+    }
+
+    /// The voice used by the OpenAI mode when none has been set, which is the first configured
+    /// model and voice.
+    pub fn default_voice() -> &'static str {
+        DEFAULT_VOICE.get().copied().unwrap_or("tts-1 alloy")
+    }
+
+    /// Splits a stored voice into the model and voice, falling back to the first configured
+    /// model and voice if they are no longer configured.
+    fn split_voice<'a>(&'a self, model_voice: &'a str) -> (&'a str, &'a str) {
+        let (model, voice) = model_voice.split_once(' ').unwrap_or(("", model_voice));
+
+        let model = match self.models.iter().find(|m| m.as_str() == model) {
+            Some(model) => model.as_str(),
+            None => self.models[0].as_str(),
+        };
+
+        let voice = match self.voices.iter().find(|v| v.as_str() == voice) {
+            Some(voice) => voice.as_str(),
+            None => self.voices[0].as_str(),
+        };
+
+        (model, voice)
+    }
+
+    async fn send(&self, body: &SpeechRequest<'_>) -> Result<reqwest::Response> {
+        let url = self.base_url.join("v1/audio/speech")?;
+        let mut req = self.reqwest.post(url).json(body);
+        if let Some(api_key) = &self.api_key {
+            req = req.bearer_auth(api_key.as_str());
+        }
+
+        Ok(req.send().await?)
+    }
+}
+
+#[serenity::async_trait]
+impl TtsBackend for OpenAIBackend {
+    fn name(&self) -> &'static str {
+        "openai"
+    }
+
+    fn supports(&self, mode: TTSMode) -> bool {
+        mode == TTSMode::OpenAI
+    }
+
+    async fn fetch_voices(&self, _mode: TTSMode) -> Result<serde_json::Value> {
+        let voices: Vec<_> = self
+            .models
+            .iter()
+            .flat_map(|model| {
+                self.voices
+                    .iter()
+                    .map(move |voice| format!("{model} {voice}"))
+            })
+            .collect();
+
+        Ok(serde_json::Value::from(voices))
+    }
+
+    async fn synthesise(&self, request: SynthesisRequest<'_>) -> Result<Option<Audio>> {
+        let (model, voice) = self.split_voice(request.voice);
+        let mut body = SpeechRequest {
+            model,
+            voice,
+            input: request.text,
+            speed: request.speaking_rate.parse().unwrap_or(1.0),
+            response_format: if self.mp3_only.load(Ordering::Relaxed) {
+                "mp3"
+            } else {
+                request.mode.preferred_format()
+            },
+        };
+
+        let resp = self.send(&body).await?;
+        let resp = match unless_format_error(body.response_format, resp).await? {
+            Some(resp) => resp,
+            None => {
+                body.response_format = "mp3";
+                let resp = self.send(&body).await?;
+
+                if resp.status().is_success() {
+                    tracing::warn!(
+                        "OpenAI TTS: The endpoint only supports mp3, so it will be used from now on"
+                    );
+                    self.mp3_only.store(true, Ordering::Relaxed);
+                }
+
+                resp
+            }
+        };
+
+        let resp = resp.error_for_status()?;
+        let mime_type = match body.response_format {
+            "opus" => "audio/ogg",
+            _ => "audio/mpeg",
+        };
+
+        Ok(Some(Audio {
+            data: AudioData::Streaming(resp),
+            mime_type: Some(String::from(mime_type)),
+        }))
+    }
+}
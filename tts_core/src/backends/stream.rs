@@ -0,0 +1,61 @@
+use std::{
+    io::SeekFrom,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use serenity::futures::TryStreamExt as _;
+use songbird::input::AsyncMediaSource;
+use tokio::io::{AsyncRead, AsyncSeek, ReadBuf};
+use tokio_util::io::StreamReader;
+
+/// Reads audio from a response body as it is downloaded, so playback can start before the
+/// tts-service has finished sending it.
+pub struct ResponseStream {
+    reader: Box<dyn AsyncRead + Send + Sync + Unpin>,
+    len: Option<u64>,
+}
+
+impl ResponseStream {
+    #[must_use]
+    pub fn new(resp: reqwest::Response) -> Self {
+        let len = resp.content_length();
+        let body = resp.bytes_stream().map_err(std::io::Error::other);
+
+        Self {
+            reader: Box::new(StreamReader::new(body)),
+            len,
+        }
+    }
+}
+
+impl AsyncRead for ResponseStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.reader).poll_read(cx, buf)
+    }
+}
+
+impl AsyncSeek for ResponseStream {
+    fn start_seek(self: Pin<&mut Self>, _position: SeekFrom) -> std::io::Result<()> {
+        Err(std::io::ErrorKind::Unsupported.into())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<u64>> {
+        unreachable!("start_seek always fails")
+    }
+}
+
+#[serenity::async_trait]
+impl AsyncMediaSource for ResponseStream {
+    fn is_seekable(&self) -> bool {
+        false
+    }
+
+    async fn byte_len(&self) -> Option<u64> {
+        self.len
+    }
+}
@@ -6,7 +6,9 @@ use rand::Rng as _;
 
 use serenity::all as serenity;
 use serenity::{CreateActionRow, CreateButton};
+use songbird::input::{core::io::MediaSource, AsyncAdapterStream};
 
+use crate::backends::{Audio, AudioData, ResponseStream};
 use crate::structs::{
     Context, Data, LastToXsaidTracker, LastXsaidInfo, RegexCache, Result, TTSMode, TTSServiceError,
 };
@@ -65,17 +67,8 @@ pub async fn dm_generic<'ctx, 'a>(
     Ok((target_tag, sent.embeds.into_iter().next().unwrap()))
 }
 
-pub async fn fetch_audio(
-    reqwest: &reqwest::Client,
-    url: reqwest::Url,
-    auth_key: Option<&str>,
-) -> Result<Option<reqwest::Response>> {
-    let resp = reqwest
-        .get(url)
-        .header(reqwest::header::AUTHORIZATION, auth_key.unwrap_or(""))
-        .send()
-        .await?;
-
+/// Parses any error returned by the tts-service, returning `None` if the error should be ignored.
+pub async fn check_audio_response(resp: reqwest::Response) -> Result<Option<reqwest::Response>> {
     match resp.error_for_status_ref() {
         Ok(_) => Ok(Some(resp)),
         Err(backup_err) => match resp.json::<TTSServiceError>().await {
@@ -83,7 +76,7 @@ pub async fn fetch_audio(
                 if err.code.should_ignore() {
                     Ok(None)
                 } else {
-                    Err(anyhow::anyhow!("Error fetching audio: {}", err.display))
+                    Err(anyhow::Error::new(err).context("Error fetching audio"))
                 }
             }
             Err(_) => Err(backup_err.into()),
@@ -91,6 +84,33 @@ pub async fn fetch_audio(
     }
 }
 
+/// How much of a streamed response is read ahead of playback.
+const STREAM_BUFFER_LEN: usize = 64 * 1024;
+
+/// Converts audio into a songbird input, streaming it if it is still downloading.
+///
+/// This must be called within the tokio runtime, as streaming spawns a task to read the audio.
+pub fn audio_to_input(audio: Audio) -> songbird::input::Input {
+    let hint = audio.mime_type.map(|mime_type| {
+        let mut hint = songbird::input::core::probe::Hint::new();
+        hint.mime_type(&mime_type);
+        hint
+    });
+
+    let input: Box<dyn MediaSource> = match audio.data {
+        AudioData::Buffered(data) => Box::new(std::io::Cursor::new(data)),
+        AudioData::Streaming(resp) => {
+            let stream = Box::new(ResponseStream::new(resp));
+            Box::new(AsyncAdapterStream::new(stream, STREAM_BUFFER_LEN))
+        }
+    };
+
+    let wrapped_audio =
+        songbird::input::LiveInput::Raw(songbird::input::AudioStream { input, hint });
+
+    songbird::input::Input::Live(wrapped_audio, None)
+}
+
 pub fn prepare_url(
     mut tts_service: reqwest::Url,
     content: &str,
@@ -99,6 +119,7 @@ pub fn prepare_url(
     speaking_rate: &str,
     max_length: &str,
     translation_lang: Option<&str>,
+    format: &str,
 ) -> reqwest::Url {
     {
         let mut params = tts_service.query_pairs_mut();
@@ -106,7 +127,7 @@ pub fn prepare_url(
         params.append_pair("lang", lang);
         params.append_pair("mode", mode.into());
         params.append_pair("max_length", max_length);
-        params.append_pair("preferred_format", "mp3");
+        params.append_pair("preferred_format", format);
         params.append_pair("speaking_rate", speaking_rate);
 
         if let Some(translation_lang) = translation_lang {
@@ -1,21 +1,75 @@
-use std::{hash::Hash, sync::Arc};
+use std::{
+    hash::Hash,
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
 use dashmap::DashMap;
+use itertools::Itertools as _;
+use mini_moka::sync::ConcurrentCacheExt as _;
+use tokio::sync::OnceCell;
 use typesize::TypeSize;
 
 pub use crate::database_models::*;
 use crate::structs::{Result, TTSMode};
 
-type PgArguments<'a> = <sqlx::Postgres as sqlx::database::Database>::Arguments<'a>;
-type QueryAs<'a, R> = sqlx::query::QueryAs<'a, sqlx::Postgres, R, PgArguments<'a>>;
-type Query<'a> = sqlx::query::Query<'a, sqlx::Postgres, PgArguments<'a>>;
+/// The database the bot stores data in, `SQLite` if built with the `sqlite` feature.
+#[cfg(not(feature = "sqlite"))]
+pub type Db = sqlx::Postgres;
+#[cfg(feature = "sqlite")]
+pub type Db = sqlx::Sqlite;
+
+pub type Pool = sqlx::Pool<Db>;
+pub type DbRow = <Db as sqlx::Database>::Row;
+pub type DbConnection = <Db as sqlx::Database>::Connection;
+
+/// The date of yesterday, for comparing against `date` columns.
+#[cfg(not(feature = "sqlite"))]
+pub const YESTERDAY: &str = "CURRENT_DATE - 1";
+#[cfg(feature = "sqlite")]
+pub const YESTERDAY: &str = "date('now', '-1 day')";
+
+/// Prefixes the values of a multi-column update, as Postgres requires `ROW` when setting one column.
+#[doc(hidden)]
+#[cfg(not(feature = "sqlite"))]
+pub const ROW_CONSTRUCTOR: &str = "ROW";
+#[doc(hidden)]
+#[cfg(feature = "sqlite")]
+pub const ROW_CONSTRUCTOR: &str = "";
+
+/// Formats `count` parameters starting from `$start`, for an `IN` list.
+///
+/// This is used instead of `= ANY($1)`, as `SQLite` cannot bind arrays.
+#[must_use]
+pub fn placeholders(start: usize, count: usize) -> String {
+    (start..start + count).map(|i| format!("${i}")).join(", ")
+}
+
+/// Runs a trivial query, to check the database can be reached.
+pub async fn ping(pool: &Pool) -> Result<()> {
+    sqlx::query("SELECT 1").execute(pool).await?;
+    Ok(())
+}
+
+type DbArguments<'a> = <Db as sqlx::database::Database>::Arguments<'a>;
+type QueryAs<'a, R> = sqlx::query::QueryAs<'a, Db, R, DbArguments<'a>>;
+type Query<'a> = sqlx::query::Query<'a, Db, DbArguments<'a>>;
+
+pub trait CacheKeyTrait: std::cmp::Eq + Hash + Send + Sync + 'static {
+    /// How many query parameters [`Self::bind_query`] binds.
+    const PARAM_COUNT: usize;
 
-pub trait CacheKeyTrait: std::cmp::Eq + Hash {
     fn bind_query(self, query: Query<'_>) -> Query<'_>;
     fn bind_query_as<R>(self, query: QueryAs<'_, R>) -> QueryAs<'_, R>;
 }
 
 impl CacheKeyTrait for i64 {
+    const PARAM_COUNT: usize = 1;
+
     fn bind_query(self, query: Query<'_>) -> Query<'_> {
         query.bind(self)
     }
@@ -25,6 +79,8 @@ impl CacheKeyTrait for i64 {
 }
 
 impl CacheKeyTrait for [i64; 2] {
+    const PARAM_COUNT: usize = 2;
+
     fn bind_query(self, query: Query<'_>) -> Query<'_> {
         query.bind(self[0]).bind(self[1])
     }
@@ -34,6 +90,8 @@ impl CacheKeyTrait for [i64; 2] {
 }
 
 impl CacheKeyTrait for (i64, TTSMode) {
+    const PARAM_COUNT: usize = 2;
+
     fn bind_query(self, query: Query<'_>) -> Query<'_> {
         query.bind(self.0).bind(self.1)
     }
@@ -42,14 +100,99 @@ impl CacheKeyTrait for (i64, TTSMode) {
     }
 }
 
-type OwnedArc<T> = typesize::ptr::SizableArc<T, typesize::ptr::Owned>;
+type Binder = Box<dyn for<'q> FnOnce(Query<'q>) -> Query<'q> + Send>;
+
+/// Several columns of a row to write at once, see [`Handler::update`].
+///
+/// This is started with the `update` function on each row type, such as `GuildRow::update`,
+/// which has a typed setter for each column.
+pub struct Update<RowT> {
+    columns: Vec<(&'static str, Binder)>,
+    _row: PhantomData<fn() -> RowT>,
+}
+
+impl<RowT> Default for Update<RowT> {
+    fn default() -> Self {
+        Self {
+            columns: Vec::new(),
+            _row: PhantomData,
+        }
+    }
+}
+
+impl<RowT> Update<RowT> {
+    pub(crate) fn set<Val>(mut self, column: &'static str, value: Val) -> Self
+    where
+        for<'a> Val: sqlx::Encode<'a, Db>,
+        Val: sqlx::Type<Db>,
+        Val: Send + 'static,
+    {
+        let bind: Binder = Box::new(move |query| query.bind(value));
+        self.columns.push((column, bind));
+        self
+    }
+}
+
+/// The `[Database-Cache.{table}]` config section, for how many rows are cached and for how long.
+#[derive(serde::Deserialize, Clone, Copy, Default)]
+pub struct CacheConfig {
+    pub capacity: Option<u64>,
+    pub idle_secs: Option<u64>,
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Worked out from the other counters, so it can be off while rows are being inserted.
+    pub evictions: u64,
+    pub entries: u64,
+}
+
+impl CacheStats {
+    #[must_use]
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64 * 100.0
+        }
+    }
+}
+
+impl std::ops::Add for CacheStats {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            hits: self.hits + rhs.hits,
+            misses: self.misses + rhs.misses,
+            evictions: self.evictions + rhs.evictions,
+            entries: self.entries + rhs.entries,
+        }
+    }
+}
+
+#[derive(Default)]
+struct CacheCounters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    inserts: AtomicU64,
+    invalidations: AtomicU64,
+}
+
+type Row<RowT> = Arc<<RowT as Compact>::Compacted>;
 
 pub struct Handler<CacheKey, RowT: Compact> {
-    pool: sqlx::PgPool,
-    cache: DashMap<CacheKey, OwnedArc<RowT::Compacted>>,
+    pool: Pool,
+    cache: mini_moka::sync::Cache<CacheKey, Row<RowT>>,
+    /// Rows currently being loaded, so concurrent misses for a key only query once.
+    loading: DashMap<CacheKey, Arc<OnceCell<Row<RowT>>>>,
+    counters: CacheCounters,
 
     default_row: Arc<RowT::Compacted>,
     single_insert: &'static str,
+    multi_insert: &'static str,
     create_row: &'static str,
     select: &'static str,
     delete: &'static str,
@@ -58,33 +201,46 @@ pub struct Handler<CacheKey, RowT: Compact> {
 impl<CacheKey, RowT> Handler<CacheKey, RowT>
 where
     CacheKey: CacheKeyTrait + Sync + Send + Copy + Default,
-    RowT: for<'r> sqlx::FromRow<'r, sqlx::postgres::PgRow> + Compact + Send + Unpin,
+    RowT: for<'r> sqlx::FromRow<'r, DbRow> + Compact + Send + Unpin,
 {
     pub async fn new(
-        pool: sqlx::PgPool,
+        pool: Pool,
+        cache_config: CacheConfig,
         select: &'static str,
         delete: &'static str,
         create_row: &'static str,
         single_insert: &'static str,
+        multi_insert: &'static str,
     ) -> Result<Self> {
         let default_row = Self::_get(&pool, CacheKey::default(), select)
             .await?
             .expect("Default row not in table!");
 
+        let cache = mini_moka::sync::Cache::builder()
+            .max_capacity(cache_config.capacity.unwrap_or(100_000))
+            .time_to_idle(Duration::from_secs(
+                cache_config.idle_secs.unwrap_or(60 * 60),
+            ))
+            .build();
+
         println!("Loaded default row for table with select: {select}");
         Ok(Self {
-            cache: DashMap::new(),
+            cache,
+            loading: DashMap::new(),
+            counters: CacheCounters::default(),
             default_row,
             pool,
             select,
             delete,
             create_row,
             single_insert,
+            multi_insert,
         })
     }
 
+    #[tracing::instrument(name = "database_load", skip_all, fields(query = select))]
     async fn _get(
-        pool: &sqlx::PgPool,
+        pool: &Pool,
         key: CacheKey,
         select: &'static str,
     ) -> Result<Option<Arc<RowT::Compacted>>> {
@@ -95,15 +251,35 @@ where
 
     pub async fn get(&self, identifier: CacheKey) -> Result<Arc<RowT::Compacted>> {
         if let Some(row) = self.cache.get(&identifier) {
-            return Ok(row.clone());
+            self.counters.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(row);
         }
 
-        let row = Self::_get(&self.pool, identifier, self.select)
-            .await?
-            .unwrap_or_else(|| self.default_row.clone());
+        self.counters.misses.fetch_add(1, Ordering::Relaxed);
+        let loading = self.loading.entry(identifier).or_default().clone();
+        let result = loading
+            .get_or_try_init(|| async {
+                let row = Self::_get(&self.pool, identifier, self.select).await?;
+                Ok::<_, anyhow::Error>(row.unwrap_or_else(|| self.default_row.clone()))
+            })
+            .await
+            .cloned();
+
+        // If the row was invalidated while loading, it may be outdated so should not be cached.
+        //
+        // The row is cached while the loading entry is locked, so an invalidation either removes
+        // the entry first and the row is not cached, or runs afterwards and removes the row.
+        self.loading.remove_if(&identifier, |_, cell| {
+            let is_current = Arc::ptr_eq(cell, &loading);
+            if is_current && let Ok(row) = &result {
+                self.cache.insert(identifier, row.clone());
+                self.counters.inserts.fetch_add(1, Ordering::Relaxed);
+            }
 
-        self.cache.insert(identifier, row.clone().into());
-        Ok(row)
+            is_current
+        });
+
+        result
     }
 
     pub async fn create_row(&self, identifier: CacheKey) -> Result<()> {
@@ -122,8 +298,8 @@ where
         value: Val,
     ) -> Result<()>
     where
-        for<'a> Val: sqlx::Encode<'a, sqlx::Postgres>,
-        Val: sqlx::Type<sqlx::Postgres>,
+        for<'a> Val: sqlx::Encode<'a, Db>,
+        Val: sqlx::Type<Db>,
         Val: Sync + Send,
     {
         let query_raw = self.single_insert.replace("{key}", key);
@@ -138,6 +314,33 @@ where
         Ok(())
     }
 
+    /// Writes every column in the update in a single statement, creating the row if needed.
+    pub async fn update(&self, identifier: CacheKey, update: Update<RowT>) -> Result<()> {
+        if update.columns.is_empty() {
+            return Ok(());
+        }
+
+        let columns = update.columns.iter().map(|(column, _)| column).join(", ");
+        let values = (1..=update.columns.len())
+            .map(|i| format!("${}", i + CacheKey::PARAM_COUNT))
+            .join(", ");
+
+        let query_raw = self
+            .multi_insert
+            .replace("{columns}", &columns)
+            .replace("{values}", &values);
+
+        let mut query = identifier.bind_query(sqlx::query(&query_raw));
+        for (_, bind) in update.columns {
+            query = bind(query);
+        }
+
+        query.execute(&self.pool).await?;
+
+        self.invalidate_cache(&identifier);
+        Ok(())
+    }
+
     pub async fn delete(&self, identifier: CacheKey) -> Result<()> {
         identifier
             .bind_query(sqlx::query(self.delete))
@@ -149,37 +352,72 @@ where
     }
 
     pub fn invalidate_cache(&self, identifier: &CacheKey) {
-        self.cache.remove(identifier);
+        self.loading.remove(identifier);
+        if self.cache.contains_key(identifier) {
+            self.cache.invalidate(identifier);
+            self.counters.invalidations.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Drops every cached row, for when invalidations may have been missed.
+    pub fn invalidate_all(&self) {
+        self.loading.clear();
+
+        // Counted so the rows are not reported as evictions.
+        self.cache.sync();
+        let entries = self.cache.entry_count();
+        self.cache.invalidate_all();
+        self.counters
+            .invalidations
+            .fetch_add(entries, Ordering::Relaxed);
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        // The entry count lags behind until pending maintenance has run.
+        self.cache.sync();
+        let entries = self.cache.entry_count();
+        let inserts = self.counters.inserts.load(Ordering::Relaxed);
+        let invalidations = self.counters.invalidations.load(Ordering::Relaxed);
+
+        CacheStats {
+            hits: self.counters.hits.load(Ordering::Relaxed),
+            misses: self.counters.misses.load(Ordering::Relaxed),
+            // mini-moka does not report evictions, so count the rows that left without being invalidated.
+            evictions: inserts.saturating_sub(invalidations + entries),
+            entries,
+        }
     }
 }
 
-impl<CacheKey: Eq + Hash + TypeSize, RowT: Compact> TypeSize for Handler<CacheKey, RowT>
+impl<CacheKey, RowT: Compact> TypeSize for Handler<CacheKey, RowT>
 where
+    CacheKey: CacheKeyTrait + TypeSize,
     RowT::Compacted: TypeSize,
 {
     fn extra_size(&self) -> usize {
-        self.cache.extra_size()
+        self.cache
+            .iter()
+            .map(|entry| entry.key().get_size() + entry.value().as_ref().get_size())
+            .sum()
     }
 
     typesize::if_typesize_details! {
         fn get_collection_item_count(&self) -> Option<usize> {
-            self.cache.get_collection_item_count()
-        }
-
-        fn get_size_details(&self) -> Vec<typesize::Field> {
-            self.cache.get_size_details()
+            Some(self.cache.entry_count() as usize)
         }
     }
 }
 
 #[macro_export]
 macro_rules! create_db_handler {
-    ($pool:expr, $table_name:literal, $id_name:literal) => {{
+    ($pool:expr, $cache_config:expr, $table_name:literal, $id_name:literal) => {{
         const TABLE_NAME: &str = $table_name;
         const ID_NAME: &str = $id_name;
+        const ROW: &str = $crate::database::ROW_CONSTRUCTOR;
 
         database::Handler::new(
             $pool,
+            $cache_config.get(TABLE_NAME).copied().unwrap_or_default(),
             const_format::formatcp!("SELECT * FROM {TABLE_NAME} WHERE {ID_NAME} = $1"),
             const_format::formatcp!("DELETE FROM {TABLE_NAME} WHERE {ID_NAME} = $1"),
             const_format::formatcp!(
@@ -190,15 +428,21 @@ macro_rules! create_db_handler {
                 "INSERT INTO {TABLE_NAME}({ID_NAME}, {{key}}) VALUES ($1, $2)
                 ON CONFLICT ({ID_NAME}) DO UPDATE SET {{key}} = $2"
             ),
+            const_format::formatcp!(
+                "INSERT INTO {TABLE_NAME}({ID_NAME}, {{columns}}) VALUES ($1, {{values}})
+                ON CONFLICT ({ID_NAME}) DO UPDATE SET ({{columns}}) = {ROW}({{values}})"
+            ),
         )
     }};
-    ($pool:expr, $table_name:literal, $id_name1:literal, $id_name2:literal) => {{
+    ($pool:expr, $cache_config:expr, $table_name:literal, $id_name1:literal, $id_name2:literal) => {{
         const TABLE_NAME: &str = $table_name;
         const ID_NAME1: &str = $id_name1;
         const ID_NAME2: &str = $id_name2;
+        const ROW: &str = $crate::database::ROW_CONSTRUCTOR;
 
         database::Handler::new(
             $pool,
+            $cache_config.get(TABLE_NAME).copied().unwrap_or_default(),
             const_format::formatcp!(
                 "SELECT * FROM {TABLE_NAME} WHERE {ID_NAME1} = $1 AND {ID_NAME2} = $2"
             ),
@@ -213,6 +457,10 @@ macro_rules! create_db_handler {
                 "INSERT INTO {TABLE_NAME}({ID_NAME1}, {ID_NAME2}, {{key}}) VALUES ($1, $2, $3)
                 ON CONFLICT ({ID_NAME1}, {ID_NAME2}) DO UPDATE SET {{key}} = $3"
             ),
+            const_format::formatcp!(
+                "INSERT INTO {TABLE_NAME}({ID_NAME1}, {ID_NAME2}, {{columns}}) VALUES ($1, $2, {{values}})
+                ON CONFLICT ({ID_NAME1}, {ID_NAME2}) DO UPDATE SET ({{columns}}) = {ROW}({{values}})"
+            ),
         )
     }};
 }
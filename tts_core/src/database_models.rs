@@ -3,9 +3,15 @@ use std::num::NonZeroU8;
 use arrayvec::ArrayString;
 use typesize::derive::TypeSize;
 
-use poise::serenity_prelude::{ChannelId, GuildId, RoleId, UserId};
+use poise::serenity_prelude::{
+    small_fixed_array::{FixedArray, TruncatingInto as _},
+    ChannelId, GuildId, RoleId, UserId,
+};
 
-use crate::structs::{IsPremium, TTSMode};
+use crate::{
+    database::Update,
+    structs::{IsPremium, ModeList, TTSMode},
+};
 
 const MAX_VOICE_LENGTH: usize = 20;
 
@@ -21,8 +27,32 @@ fn truncate_convert<const MAX_SIZE: usize>(
     ArrayString::from(&s).expect("Truncate to shrink to below the max size!")
 }
 
+/// Generates `Row::update` and a setter on [`Update`] for each column.
+///
+/// Each column is checked against the raw row, so a missing column or wrong type fails to compile.
+macro_rules! update_columns {
+    ($row:ident => $raw:ident { $($column:ident: $type:ty),* $(,)? }) => {
+        impl $row {
+            #[must_use]
+            pub fn update() -> Update<$raw> {
+                Update::default()
+            }
+        }
+
+        impl Update<$raw> {
+            $(
+                #[must_use]
+                pub fn $column(self, value: $type) -> Self {
+                    let _ = |row: $raw| -> $type { row.$column };
+                    self.set(stringify!($column), value)
+                }
+            )*
+        }
+    };
+}
+
 pub trait Compact {
-    type Compacted;
+    type Compacted: Send + Sync + 'static;
     fn compact(self) -> Self::Compacted;
 }
 
@@ -46,6 +76,7 @@ pub struct GuildRowRaw {
     pub target_lang: Option<String>,
     pub required_prefix: Option<String>,
     pub voice_mode: TTSMode,
+    pub fallback_modes: ModeList,
 }
 
 #[bool_to_bitflags::bool_to_bitflags(owning_setters)]
@@ -68,6 +99,7 @@ pub struct GuildRow {
     pub target_lang: Option<ArrayString<8>>,
     pub required_prefix: Option<ArrayString<8>>,
     pub voice_mode: TTSMode,
+    pub fallback_modes: FixedArray<TTSMode, u8>,
 }
 
 impl GuildRow {
@@ -81,8 +113,41 @@ impl GuildRow {
             None
         }
     }
+
+    /// The modes to try, in order, if `mode` fails to generate audio.
+    ///
+    /// If the server has not set any, this is just [`TTSMode::fallback`].
+    pub fn fallback_modes(&self, mode: TTSMode) -> impl Iterator<Item = TTSMode> + '_ {
+        let default = self.fallback_modes.is_empty().then(|| mode.fallback());
+        self.fallback_modes
+            .iter()
+            .copied()
+            .chain(default)
+            .filter(move |fallback| *fallback != mode)
+    }
 }
 
+update_columns!(GuildRow => GuildRowRaw {
+    channel: i64,
+    premium_user: Option<i64>,
+    required_role: Option<i64>,
+    xsaid: bool,
+    auto_join: bool,
+    bot_ignore: bool,
+    skip_emoji: bool,
+    to_translate: bool,
+    require_voice: bool,
+    text_in_voice: bool,
+    audience_ignore: bool,
+    msg_length: i16,
+    repeated_chars: i16,
+    prefix: String,
+    target_lang: Option<String>,
+    required_prefix: Option<String>,
+    voice_mode: TTSMode,
+    fallback_modes: ModeList,
+});
+
 impl Compact for GuildRowRaw {
     type Compacted = GuildRow;
     fn compact(self) -> Self::Compacted {
@@ -101,6 +166,7 @@ impl Compact for GuildRowRaw {
                 .required_prefix
                 .map(|t| truncate_convert(t, "guild.required_prefix")),
             voice_mode: self.voice_mode,
+            fallback_modes: self.fallback_modes.0.trunc_into(),
         }
         .set_xsaid(self.xsaid)
         .set_auto_join(self.auto_join)
@@ -134,6 +200,15 @@ pub struct UserRow {
     pub premium_voice_mode: Option<TTSMode>,
 }
 
+update_columns!(UserRow => UserRowRaw {
+    dm_blocked: bool,
+    dm_welcomed: bool,
+    bot_banned: bool,
+    use_new_formatting: bool,
+    voice_mode: Option<TTSMode>,
+    premium_voice_mode: Option<TTSMode>,
+});
+
 impl Compact for UserRowRaw {
     type Compacted = UserRow;
     fn compact(self) -> Self::Compacted {
@@ -164,6 +239,8 @@ pub struct GuildVoiceRow {
     pub voice: ArrayString<MAX_VOICE_LENGTH>,
 }
 
+update_columns!(GuildVoiceRow => GuildVoiceRowRaw { voice: String });
+
 impl Compact for GuildVoiceRowRaw {
     type Compacted = GuildVoiceRow;
     fn compact(self) -> Self::Compacted {
@@ -191,6 +268,11 @@ pub struct UserVoiceRow {
     pub speaking_rate: Option<f32>,
 }
 
+update_columns!(UserVoiceRow => UserVoiceRowRaw {
+    voice: Option<String>,
+    speaking_rate: Option<f32>,
+});
+
 impl Compact for UserVoiceRowRaw {
     type Compacted = UserVoiceRow;
     fn compact(self) -> Self::Compacted {
@@ -212,6 +294,8 @@ pub struct NicknameRow {
 
 pub type NicknameRowRaw = NicknameRow;
 
+update_columns!(NicknameRow => NicknameRow { name: Option<String> });
+
 impl Compact for NicknameRowRaw {
     type Compacted = NicknameRow;
     fn compact(self) -> Self::Compacted {
@@ -0,0 +1,184 @@
+use std::{collections::BTreeMap, sync::OnceLock};
+
+use poise::serenity_prelude::small_fixed_array::FixedString;
+
+use crate::{
+    backends::TtsBackend,
+    database,
+    structs::{Result, SpeakingRateInfo, TTSMode},
+    voice_lists,
+};
+
+static DYNAMIC_MODES: OnceLock<Vec<DynamicMode>> = OnceLock::new();
+
+/// How a mode's voice list is returned by `/voices?raw=true`.
+#[derive(serde::Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum VoiceListShape {
+    /// A list of voice codes, such as `["en1", "en2"]`.
+    #[default]
+    List,
+    /// A map of voice codes to display names, such as `{"en": "English"}`.
+    Map,
+}
+
+#[derive(serde::Deserialize)]
+pub struct SpeakingRateRange {
+    pub min: f32,
+    pub default: String,
+    pub max: f32,
+    pub kind: String,
+}
+
+/// A mode declared by the tts-service's `/modes` endpoint, or a `[[TTS-Mode]]` config section.
+#[derive(serde::Deserialize)]
+pub struct ModeInfo {
+    pub name: String,
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub premium: bool,
+    pub default_voice: String,
+    pub speaking_rate: Option<SpeakingRateRange>,
+    #[serde(default)]
+    pub voice_list: VoiceListShape,
+    /// The audio format to request, such as `opus` or `mp3`, defaulting to `mp3`.
+    pub preferred_format: Option<String>,
+}
+
+/// A mode that was registered at startup, referenced by [`TTSMode::Dynamic`].
+///
+/// These are stored in a static once registered, so can be borrowed for `'static`.
+pub struct DynamicMode {
+    pub name: String,
+    pub display_name: String,
+    pub premium: bool,
+    pub default_voice: String,
+    speaking_rate: Option<SpeakingRateRange>,
+    pub voice_list: VoiceListShape,
+    pub preferred_format: String,
+}
+
+impl DynamicMode {
+    #[must_use]
+    pub fn speaking_rate(&'static self) -> Option<SpeakingRateInfo> {
+        self.speaking_rate.as_ref().map(|rate| SpeakingRateInfo {
+            min: rate.min,
+            max: rate.max,
+            default: &rate.default,
+            kind: &rate.kind,
+        })
+    }
+}
+
+impl From<ModeInfo> for DynamicMode {
+    fn from(info: ModeInfo) -> Self {
+        Self {
+            display_name: info.display_name.unwrap_or_else(|| info.name.clone()),
+            name: info.name,
+            premium: info.premium,
+            default_voice: info.default_voice,
+            voice_list: info.voice_list,
+            preferred_format: info.preferred_format.unwrap_or_else(|| String::from("mp3")),
+            speaking_rate: info.speaking_rate,
+        }
+    }
+}
+
+fn validate_name(name: &str) -> Result<()> {
+    let is_valid_char = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_';
+    if name.is_empty() || name.len() > 32 || !name.chars().all(is_valid_char) {
+        anyhow::bail!("Mode name `{name}` must be 1-32 characters of a-z, 0-9, or _");
+    }
+
+    if TTSMode::CORE.iter().any(|mode| mode.db_name() == name) {
+        anyhow::bail!("Mode name `{name}` conflicts with a built in mode");
+    }
+
+    Ok(())
+}
+
+/// Registers the modes for the lifetime of the program, skipping duplicate names.
+fn register(modes: Vec<ModeInfo>) -> Result<()> {
+    let mut registered: Vec<DynamicMode> = Vec::with_capacity(modes.len());
+    for mode in modes {
+        validate_name(&mode.name)?;
+        if registered.iter().any(|m| m.name == mode.name) {
+            continue;
+        }
+
+        registered.push(DynamicMode::from(mode));
+    }
+
+    if registered.len() > usize::from(u8::MAX) {
+        anyhow::bail!("Only {} dynamic modes can be registered", u8::MAX);
+    }
+
+    if DYNAMIC_MODES.set(registered).is_err() {
+        anyhow::bail!("Dynamic modes have already been registered");
+    }
+
+    Ok(())
+}
+
+/// Registers the modes declared in the config, then any extra modes provided by the backends.
+///
+/// This must be called once, before any [`TTSMode`] is loaded from the database.
+pub async fn load(
+    pool: &database::Pool,
+    tts_backends: &[Box<dyn TtsBackend>],
+    mut modes: Vec<ModeInfo>,
+) -> Result<()> {
+    for backend in tts_backends {
+        let kind = format!("modes:{}", backend.name());
+        let raw = voice_lists::fetch_or_cached(pool, &kind, backend.fetch_modes().await).await?;
+        if let Some(raw) = raw {
+            modes.extend(serde_json::from_value::<Vec<ModeInfo>>(raw)?);
+        }
+    }
+
+    register(modes)?;
+
+    #[cfg(not(feature = "sqlite"))]
+    add_db_values(pool).await?;
+    Ok(())
+}
+
+/// Adds each registered mode to the `TTSMode` database enum, so they can be stored.
+///
+/// `SQLite` stores modes as text, so has no enum to add to.
+#[cfg(not(feature = "sqlite"))]
+async fn add_db_values(pool: &database::Pool) -> Result<()> {
+    for mode in all() {
+        // The name has been validated, and `ALTER TYPE` cannot take bind parameters.
+        let query = format!("ALTER TYPE TTSMode ADD VALUE IF NOT EXISTS '{}'", mode.name);
+        sqlx::query(&query).execute(pool).await?;
+    }
+
+    Ok(())
+}
+
+#[must_use]
+pub fn all() -> &'static [DynamicMode] {
+    DYNAMIC_MODES.get().map(Vec::as_slice).unwrap_or_default()
+}
+
+#[must_use]
+pub fn get(index: u8) -> &'static DynamicMode {
+    &all()[usize::from(index)]
+}
+
+/// Converts a raw voice list into a map of voice codes to names.
+pub fn parse_voices(
+    shape: VoiceListShape,
+    raw: serde_json::Value,
+) -> Result<BTreeMap<FixedString<u8>, FixedString<u8>>> {
+    Ok(match shape {
+        // No backend supports the mode, so there are no voices.
+        _ if raw.is_null() => BTreeMap::new(),
+        VoiceListShape::List => serde_json::from_value::<Vec<FixedString<u8>>>(raw)?
+            .into_iter()
+            .map(|voice| (voice.clone(), voice))
+            .collect(),
+        VoiceListShape::Map => serde_json::from_value(raw)?,
+    })
+}
@@ -78,6 +78,7 @@ async fn fetch_update_occurrences(
 async fn insert_traceback(
     http: &serenity::Http,
     data: &Data,
+    webhook: &serenity::Webhook,
     embed: serenity::CreateEmbed<'_>,
     traceback: String,
     traceback_hash: Vec<u8>,
@@ -93,12 +94,7 @@ async fn insert_traceback(
         .embeds(embeds.as_slice())
         .components(components.as_slice());
 
-    let message = data
-        .webhooks
-        .errors
-        .execute(http, true, builder)
-        .await?
-        .try_unwrap()?;
+    let message = webhook.execute(http, true, builder).await?.try_unwrap()?;
 
     let ErrorRow {
         message_id: db_message_id,
@@ -117,10 +113,7 @@ async fn insert_traceback(
     .await?;
 
     if message.id != db_message_id as u64 {
-        data.webhooks
-            .errors
-            .delete_message(http, None, message.id)
-            .await?;
+        webhook.delete_message(http, None, message.id).await?;
     }
 
     Ok(())
@@ -138,6 +131,12 @@ pub async fn handle_unexpected<'a>(
     let data = poise_context.user_data();
     let ctx = poise_context.serenity_context;
 
+    let Some(webhook) = data.webhooks().errors.clone() else {
+        // Without an error webhook, the error can only be logged.
+        tracing::error!("Unexpected error in {event}: {error:?}");
+        return Ok(());
+    };
+
     let Some((traceback, traceback_hash)) = fetch_update_occurrences(&data, &error).await? else {
         return Ok(());
     };
@@ -198,7 +197,7 @@ pub async fn handle_unexpected<'a>(
         embed = embed.author(author_builder);
     }
 
-    insert_traceback(&ctx.http, &data, embed, traceback, traceback_hash).await
+    insert_traceback(&ctx.http, &data, &webhook, embed, traceback, traceback_hash).await
 }
 
 pub async fn handle_unexpected_default(
@@ -500,6 +499,9 @@ pub async fn handle(error: poise::FrameworkError<'_, Data, Error>) -> Result<()>
                 Event::Ready { .. } => {
                     handle_unexpected_default(framework, "Ready", Err(error)).await?;
                 }
+                Event::CacheReady { .. } => {
+                    handle_unexpected_default(framework, "CacheReady", Err(error)).await?;
+                }
                 _ => {
                     tracing::warn!("Unhandled {} error: {:?}", event.snake_case_name(), error);
                 }
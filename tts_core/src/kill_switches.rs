@@ -0,0 +1,80 @@
+use dashmap::DashMap;
+use poise::serenity_prelude::small_fixed_array::FixedString;
+
+use crate::{
+    database,
+    structs::{Data, Result, TTSMode},
+};
+
+// Modes are loaded as text, so kill switches for removed dynamic modes can be skipped.
+#[derive(sqlx::FromRow)]
+struct KillSwitchRow {
+    mode: String,
+    fallback: String,
+    reason: String,
+}
+
+/// A voice mode that has been globally disabled, for example if the upstream API is broken.
+pub struct KillSwitch {
+    pub fallback: TTSMode,
+    pub reason: FixedString<u16>,
+}
+
+pub async fn fetch_all(pool: &database::Pool) -> Result<DashMap<TTSMode, KillSwitch>> {
+    let rows: Vec<KillSwitchRow> = sqlx::query_as(
+        "
+        SELECT CAST(mode AS text) AS mode, CAST(fallback AS text) AS fallback, reason
+        FROM mode_kill_switches
+    ",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let kill_switches = rows.into_iter().filter_map(|row| {
+        let kill_switch = KillSwitch {
+            fallback: row.fallback.parse().ok()?,
+            reason: FixedString::from_string_trunc(row.reason),
+        };
+
+        Some((row.mode.parse().ok()?, kill_switch))
+    });
+
+    Ok(kill_switches.collect())
+}
+
+pub async fn disable_mode(
+    data: &Data,
+    mode: TTSMode,
+    fallback: TTSMode,
+    reason: FixedString<u16>,
+) -> Result<()> {
+    sqlx::query(
+        "
+        INSERT INTO mode_kill_switches(mode, fallback, reason)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (mode) DO UPDATE SET
+            fallback = EXCLUDED.fallback,
+            reason = EXCLUDED.reason
+    ",
+    )
+    .bind(mode)
+    .bind(fallback)
+    .bind(reason.as_str())
+    .execute(&data.pool)
+    .await?;
+
+    data.mode_kill_switches
+        .insert(mode, KillSwitch { fallback, reason });
+
+    Ok(())
+}
+
+/// Re-enables a mode, returning if it was disabled.
+pub async fn enable_mode(data: &Data, mode: TTSMode) -> Result<bool> {
+    sqlx::query("DELETE FROM mode_kill_switches WHERE mode = $1")
+        .bind(mode)
+        .execute(&data.pool)
+        .await?;
+
+    Ok(data.mode_kill_switches.remove(&mode).is_some())
+}
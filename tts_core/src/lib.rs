@@ -2,12 +2,20 @@
 #![allow(async_fn_in_trait)]
 
 pub mod analytics;
+pub mod backends;
 pub mod common;
 pub mod constants;
 pub mod database;
 pub mod database_models;
+pub mod dynamic_modes;
 pub mod errors;
+pub mod kill_switches;
 pub mod macros;
+pub mod metrics;
 pub mod opt_ext;
+pub mod reload;
 pub mod structs;
+pub mod tasks;
 pub mod traits;
+pub mod voice_lists;
+pub mod voice_sessions;
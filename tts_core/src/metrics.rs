@@ -0,0 +1,275 @@
+use std::{
+    collections::HashMap,
+    sync::LazyLock,
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+use poise::serenity_prelude as serenity;
+use prometheus::{
+    Encoder as _, GaugeVec, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use typesize::TypeSize as _;
+
+use crate::structs::{Data, Result};
+
+/// How often the size of each cache is measured, as that walks every cached item.
+const MEMORY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The metrics of the whole process, as some are recorded outside of [`Data`].
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+/// Every exported Prometheus metric.
+///
+/// Most counters are recorded as they happen, the rest of the metrics are filled in by
+/// [`Metrics::update`] and [`Metrics::update_shards`] before each scrape.
+pub struct Metrics {
+    registry: Registry,
+
+    pub messages: IntCounterVec,
+    pub tts_request_duration: HistogramVec,
+    pub tts_request_errors: IntCounterVec,
+    pub task_runs: IntCounterVec,
+    pub task_failures: IntCounterVec,
+    pub task_last_success: IntGaugeVec,
+
+    queue_depth: IntGauge,
+    in_flight: IntGauge,
+    voice_connections: IntGauge,
+    shard_latency: GaugeVec,
+    db_cache_entries: IntGaugeVec,
+    db_cache_hits: IntCounterVec,
+    db_cache_misses: IntCounterVec,
+    db_cache_evictions: IntCounterVec,
+    memory_bytes: IntGaugeVec,
+    memory_measured: Mutex<Option<Instant>>,
+}
+
+fn opts(name: &str, help: &str) -> Opts {
+    Opts::new(name, help).namespace("tts")
+}
+
+/// Advances a counter to a total which is counted elsewhere, such as by the database cache.
+fn catch_up(counter: &IntCounter, total: u64) {
+    let recorded = counter.get();
+    if total > recorded {
+        counter.inc_by(total - recorded);
+    }
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let duration_opts = HistogramOpts::from(opts(
+            "request_duration_seconds",
+            "How long TTS requests took, by backend",
+        ));
+
+        let metrics = Self {
+            registry: Registry::new(),
+
+            messages: IntCounterVec::new(
+                opts("messages_total", "Messages read out, by mode"),
+                &["mode"],
+            )
+            .unwrap(),
+            tts_request_duration: HistogramVec::new(duration_opts, &["backend"]).unwrap(),
+            tts_request_errors: IntCounterVec::new(
+                opts("request_errors_total", "Failed TTS requests, by backend"),
+                &["backend"],
+            )
+            .unwrap(),
+            task_runs: IntCounterVec::new(
+                opts("task_runs_total", "Runs of each background task"),
+                &["task"],
+            )
+            .unwrap(),
+            task_failures: IntCounterVec::new(
+                opts("task_failures_total", "Failed runs of each background task"),
+                &["task"],
+            )
+            .unwrap(),
+            task_last_success: IntGaugeVec::new(
+                opts(
+                    "task_last_success_timestamp_seconds",
+                    "When each background task last succeeded",
+                ),
+                &["task"],
+            )
+            .unwrap(),
+
+            queue_depth: IntGauge::with_opts(opts(
+                "request_queue_depth",
+                "TTS requests waiting to be sent",
+            ))
+            .unwrap(),
+            in_flight: IntGauge::with_opts(opts("requests_in_flight", "TTS requests being sent"))
+                .unwrap(),
+            voice_connections: IntGauge::with_opts(opts(
+                "voice_connections",
+                "Voice channels the bot is in",
+            ))
+            .unwrap(),
+            shard_latency: GaugeVec::new(
+                opts(
+                    "shard_latency_seconds",
+                    "Gateway heartbeat latency, by shard",
+                ),
+                &["shard"],
+            )
+            .unwrap(),
+            db_cache_entries: IntGaugeVec::new(
+                opts("db_cache_entries", "Rows cached, by table"),
+                &["table"],
+            )
+            .unwrap(),
+            db_cache_hits: IntCounterVec::new(
+                opts("db_cache_hits_total", "Rows found in the cache, by table"),
+                &["table"],
+            )
+            .unwrap(),
+            db_cache_misses: IntCounterVec::new(
+                opts(
+                    "db_cache_misses_total",
+                    "Rows loaded from the database, by table",
+                ),
+                &["table"],
+            )
+            .unwrap(),
+            db_cache_evictions: IntCounterVec::new(
+                opts(
+                    "db_cache_evictions_total",
+                    "Rows dropped from the cache, by table",
+                ),
+                &["table"],
+            )
+            .unwrap(),
+            memory_bytes: IntGaugeVec::new(
+                opts(
+                    "memory_bytes",
+                    "The size of each cache, according to typesize",
+                ),
+                &["cache"],
+            )
+            .unwrap(),
+            memory_measured: Mutex::new(None),
+        };
+
+        let collectors: [Box<dyn prometheus::core::Collector>; 15] = [
+            Box::new(metrics.messages.clone()),
+            Box::new(metrics.tts_request_duration.clone()),
+            Box::new(metrics.tts_request_errors.clone()),
+            Box::new(metrics.task_runs.clone()),
+            Box::new(metrics.task_failures.clone()),
+            Box::new(metrics.task_last_success.clone()),
+            Box::new(metrics.queue_depth.clone()),
+            Box::new(metrics.in_flight.clone()),
+            Box::new(metrics.voice_connections.clone()),
+            Box::new(metrics.shard_latency.clone()),
+            Box::new(metrics.db_cache_entries.clone()),
+            Box::new(metrics.db_cache_hits.clone()),
+            Box::new(metrics.db_cache_misses.clone()),
+            Box::new(metrics.db_cache_evictions.clone()),
+            Box::new(metrics.memory_bytes.clone()),
+        ];
+
+        for collector in collectors {
+            metrics.registry.register(collector).unwrap();
+        }
+
+        metrics
+    }
+
+    /// Fills in the latency of each shard, from the shard manager's runners.
+    pub fn update_shards(&self, shards: &HashMap<serenity::ShardId, serenity::ShardRunnerInfo>) {
+        self.shard_latency.reset();
+        for (id, info) in shards {
+            if let Some(latency) = info.latency {
+                self.shard_latency
+                    .with_label_values(&[&id.0.to_string()])
+                    .set(latency.as_secs_f64());
+            }
+        }
+    }
+
+    /// Fills in the metrics which are read from the bot's state, instead of recorded.
+    ///
+    /// This walks the serenity cache, so should be run with `spawn_blocking`. The size of each
+    /// cache is only measured every [`MEMORY_INTERVAL`], as that also walks every cached item.
+    pub fn update(&self, data: &Data, cache: &serenity::Cache) {
+        self.queue_depth
+            .set(data.request_limiter.queue_depth() as i64);
+        self.in_flight.set(data.request_limiter.in_flight() as i64);
+
+        let bot_user_id = cache.current_user().id;
+        let voice_connections = cache
+            .guilds()
+            .into_iter()
+            .filter_map(|id| cache.guild(id))
+            .filter(|guild| guild.voice_states.contains_key(&bot_user_id))
+            .count();
+
+        self.voice_connections.set(voice_connections as i64);
+
+        let db_stats = [
+            ("guilds", data.guilds_db.stats()),
+            ("userinfo", data.userinfo_db.stats()),
+            ("nicknames", data.nickname_db.stats()),
+            ("user_voice", data.user_voice_db.stats()),
+            ("guild_voice", data.guild_voice_db.stats()),
+        ];
+
+        for (table, stats) in db_stats {
+            let labels = [table];
+            self.db_cache_entries
+                .with_label_values(&labels)
+                .set(stats.entries as i64);
+
+            catch_up(&self.db_cache_hits.with_label_values(&labels), stats.hits);
+            catch_up(
+                &self.db_cache_misses.with_label_values(&labels),
+                stats.misses,
+            );
+            catch_up(
+                &self.db_cache_evictions.with_label_values(&labels),
+                stats.evictions,
+            );
+        }
+
+        {
+            let mut memory_measured = self.memory_measured.lock();
+            if memory_measured.is_some_and(|measured| measured.elapsed() < MEMORY_INTERVAL) {
+                return;
+            }
+
+            *memory_measured = Some(Instant::now());
+        }
+
+        let db_sizes = [
+            ("guilds_db", data.guilds_db.get_size()),
+            ("userinfo_db", data.userinfo_db.get_size()),
+            ("nicknames_db", data.nickname_db.get_size()),
+            ("user_voice_db", data.user_voice_db.get_size()),
+            ("guild_voice_db", data.guild_voice_db.get_size()),
+        ];
+
+        for (name, size) in db_sizes {
+            self.memory_bytes
+                .with_label_values(&[name])
+                .set(size as i64);
+        }
+
+        for field in cache.get_size_details() {
+            self.memory_bytes
+                .with_label_values(&[field.name])
+                .set(field.size as i64);
+        }
+    }
+
+    /// Encodes every metric in the Prometheus text format.
+    pub fn encode(&self) -> Result<String> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8(buffer)?)
+    }
+}
@@ -0,0 +1,16 @@
+use crate::structs::{Data, Result};
+
+/// The outcome of a config reload.
+#[derive(Default)]
+pub struct ReloadReport {
+    /// Changed config keys which are only used at startup, such as `Main.token`.
+    pub needs_restart: Vec<String>,
+}
+
+/// Re-reads the config and swaps in the parts that can change while running.
+///
+/// This is implemented by the main crate, as it owns config loading and logging.
+#[serenity::async_trait]
+pub trait ConfigReloader: Send + Sync {
+    async fn reload(&self, data: &Data) -> Result<ReloadReport>;
+}
@@ -1,9 +1,9 @@
 use std::{
     borrow::Cow,
-    collections::BTreeMap,
-    num::NonZeroU8,
+    collections::HashMap,
+    num::{NonZeroU8, NonZeroUsize},
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicUsize, Ordering},
         Arc, OnceLock,
     },
 };
@@ -11,10 +11,10 @@ use std::{
 use aformat::{aformat, ArrayString, CapStr};
 pub use anyhow::{Error, Result};
 use dashmap::DashMap;
-use parking_lot::Mutex;
+use parking_lot::{Mutex, RwLock};
 use serde::Deserialize as _;
 use strum_macros::IntoStaticStr;
-use tracing::warn;
+use tracing::{warn, Instrument as _};
 use typesize::derive::TypeSize;
 
 use poise::serenity_prelude::{
@@ -23,7 +23,19 @@ use poise::serenity_prelude::{
     ChannelId, GuildId, RoleId, SkuId, UserId,
 };
 
-use crate::{analytics, bool_enum, common::timestamp_in_future, database};
+use crate::{
+    analytics,
+    backends::{Audio, ModeHealth, OpenAIBackend, RequestLimiter, SynthesisRequest, TtsBackend},
+    bool_enum,
+    common::timestamp_in_future,
+    database,
+    dynamic_modes::{self, ModeInfo},
+    kill_switches::KillSwitch,
+    metrics::METRICS,
+    reload::ConfigReloader,
+    voice_lists::VoiceLists,
+    voice_sessions,
+};
 
 macro_rules! into_static_display {
     ($struct:ident, max_length($len:literal)) => {
@@ -48,14 +60,26 @@ macro_rules! into_static_display {
 pub struct Config {
     #[serde(rename = "Main")]
     pub main: MainConfig,
-    #[serde(rename = "Webhook-Info")]
+    #[serde(rename = "Webhook-Info", default)]
     pub webhooks: WebhookConfigRaw,
+    #[serde(rename = "Logging", default)]
+    pub logging: LoggingConfig,
     #[serde(rename = "Website-Info")]
     pub website_info: Option<WebsiteInfo>,
     #[serde(rename = "Premium-Info")]
     pub premium: Option<PremiumConfig>,
     #[serde(rename = "Bot-List-Tokens")]
     pub bot_list_tokens: Option<BotListTokens>,
+    #[serde(rename = "Local-TTS")]
+    pub local_tts: Option<LocalTTSConfig>,
+    #[serde(rename = "OpenAI-TTS")]
+    pub openai_tts: Option<OpenAIConfig>,
+    #[serde(rename = "TTS-Service", default)]
+    pub tts_services: Vec<TTSServiceConfig>,
+    #[serde(rename = "TTS-Mode", default)]
+    pub tts_modes: Vec<ModeInfo>,
+    #[serde(rename = "Database-Cache", default)]
+    pub db_cache: HashMap<String, database::CacheConfig>,
 }
 
 #[derive(serde::Deserialize)]
@@ -67,13 +91,59 @@ pub struct MainConfig {
     pub proxy_url: Option<FixedString>,
     pub token: Option<FixedString>,
     pub invite_channel: ChannelId,
-    pub tts_service: reqwest::Url,
+    pub tts_service: Option<reqwest::Url>,
     pub main_server: GuildId,
     pub ofs_role: RoleId,
 
-    // Only for situations where gTTS has broken
-    #[serde(default)]
-    pub gtts_disabled: AtomicBool,
+    // The most verbose level logged to the webhooks, such as `info` or `debug`
+    pub log_level: Option<FixedString>,
+    // Spoken in each voice channel before shutting down, if set
+    pub restart_notice: Option<FixedString>,
+    // How long to wait for queued messages to finish playing when shutting down
+    pub shutdown_grace_secs: Option<u64>,
+    // Timeouts for outgoing HTTP requests, defaulting to 5 and 30 seconds
+    pub connect_timeout_secs: Option<u64>,
+    pub read_timeout_secs: Option<u64>,
+    // How many times failed tts-service requests are retried, defaulting to 2
+    pub tts_max_retries: Option<u32>,
+    // How many TTS requests can be sent at once before queueing, defaulting to 64, must not be 0
+    pub tts_max_concurrency: Option<NonZeroUsize>,
+    // Serves Prometheus metrics at /metrics on this address, such as '127.0.0.1:9100'
+    pub metrics_address: Option<std::net::SocketAddr>,
+    // Serves /health/live and /health/ready on this address, such as '127.0.0.1:9101'
+    pub health_address: Option<std::net::SocketAddr>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct TTSServiceConfig {
+    pub url: reqwest::Url,
+    // Relative to the other instances, defaults to 1
+    pub weight: Option<u32>,
+}
+
+#[derive(serde::Deserialize, Clone, Copy)]
+pub enum LocalEngine {
+    #[serde(rename = "espeak-ng")]
+    EspeakNg,
+    #[serde(rename = "piper")]
+    Piper,
+}
+
+#[derive(serde::Deserialize)]
+pub struct LocalTTSConfig {
+    pub engine: LocalEngine,
+    pub binary: Option<FixedString>,
+    // Only for piper, the directory of `.onnx` voice models
+    pub voices_dir: Option<FixedString>,
+    pub default_voice: Option<FixedString>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct OpenAIConfig {
+    pub base_url: reqwest::Url,
+    pub api_key: Option<FixedString>,
+    pub models: Vec<FixedString>,
+    pub voices: Vec<FixedString>,
 }
 
 #[derive(serde::Deserialize)]
@@ -86,19 +156,51 @@ pub struct PostgresConfig {
 }
 
 #[derive(serde::Deserialize)]
+pub struct SqliteConfig {
+    pub path: String,
+}
+
+#[derive(serde::Deserialize, Clone)]
 pub struct WebsiteInfo {
     pub url: reqwest::Url,
     pub stats_key: String,
 }
 
-#[derive(serde::Deserialize)]
+// Each webhook is optional, logs and errors can be written to stdout or a file instead
+#[derive(serde::Deserialize, Default)]
 pub struct WebhookConfigRaw {
-    pub logs: reqwest::Url,
-    pub errors: reqwest::Url,
-    pub dm_logs: reqwest::Url,
+    pub logs: Option<reqwest::Url>,
+    pub errors: Option<reqwest::Url>,
+    pub dm_logs: Option<reqwest::Url>,
 }
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(serde::Deserialize, Default)]
+pub struct LoggingConfig {
+    // Writes logs to stdout, defaulting to on if `logs` in `[Webhook-Info]` is not set
+    pub stdout: Option<bool>,
+    // A directory to write log files to, which are rotated daily
+    pub directory: Option<FixedString>,
+    // How many daily log files to keep in `directory`, defaulting to 14
+    pub max_log_files: Option<usize>,
+    // The format of stdout and file logs, `text` or `json`
+    #[serde(default)]
+    pub format: LogFormat,
+    // Exports spans over OTLP/gRPC to a collector, such as 'http://localhost:4317'
+    pub otlp_endpoint: Option<FixedString>,
+    // Levels for specific targets, such as `serenity = 'info'`, instead of `log_level`
+    #[serde(default)]
+    pub targets: HashMap<String, FixedString>,
+}
+
+#[derive(serde::Deserialize, Clone)]
 pub struct BotListTokens {
     pub top_gg: FixedString,
     pub discord_bots_gg: FixedString,
@@ -114,9 +216,9 @@ pub struct PremiumConfig {
 }
 
 pub struct WebhookConfig {
-    pub logs: serenity::Webhook,
-    pub errors: serenity::Webhook,
-    pub dm_logs: serenity::Webhook,
+    pub logs: Option<serenity::Webhook>,
+    pub errors: Option<serenity::Webhook>,
+    pub dm_logs: Option<serenity::Webhook>,
 }
 
 pub struct JoinVCToken(pub GuildId, pub Arc<tokio::sync::Mutex<()>>);
@@ -132,6 +234,29 @@ impl JoinVCToken {
     }
 }
 
+/// Counts a message being read out, so shutdown can wait for its audio to be queued.
+pub struct InFlightMessage<'a>(&'a AtomicUsize);
+impl<'a> InFlightMessage<'a> {
+    /// Returns [`None`] if the bot is shutting down, and the message should be ignored.
+    pub fn start(data: &'a Data) -> Option<Self> {
+        // Counted before checking, so shutdown cannot miss a message that got past the check.
+        data.messages_in_flight.fetch_add(1, Ordering::SeqCst);
+        let message = Self(&data.messages_in_flight);
+
+        if data.shutting_down.load(Ordering::SeqCst) {
+            None
+        } else {
+            Some(message)
+        }
+    }
+}
+
+impl Drop for InFlightMessage<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
 bool_enum!(IsPremium(No | Yes));
 
 pub enum FailurePoint {
@@ -246,31 +371,35 @@ pub struct Data {
     pub entitlement_cache: mini_moka::sync::Cache<UserId, CachedEntitlement>,
     pub join_vc_tokens: DashMap<GuildId, Arc<tokio::sync::Mutex<()>>>,
     pub last_to_xsaid_tracker: LastToXsaidTracker,
-    pub startup_message: serenity::MessageId,
+    pub startup_message: Option<serenity::MessageId>,
     pub premium_avatar_url: FixedString<u16>,
     pub system_info: Mutex<sysinfo::System>,
     pub start_time: std::time::SystemTime,
     pub songbird: Arc<songbird::Songbird>,
     pub reqwest: reqwest::Client,
+    pub tts_backends: Vec<Box<dyn TtsBackend>>,
+    pub mode_health: ModeHealth,
+    pub request_limiter: RequestLimiter,
+    pub mode_kill_switches: DashMap<TTSMode, KillSwitch>,
     pub regex_cache: RegexCache,
-    pub webhooks: WebhookConfig,
-    pub pool: sqlx::PgPool,
+    pub pool: database::Pool,
 
     pub config: MainConfig,
-    pub premium_config: Option<PremiumConfig>,
+    pub config_reloader: Box<dyn ConfigReloader>,
 
-    // Startup information
+    // Swapped out by a config reload
+    pub webhooks: RwLock<Arc<WebhookConfig>>,
+    pub premium_config: RwLock<Option<Arc<PremiumConfig>>>,
     pub website_info: Mutex<Option<WebsiteInfo>>,
     pub bot_list_tokens: Mutex<Option<BotListTokens>>,
+
+    // Startup information
     pub fully_started: std::sync::atomic::AtomicBool,
+    pub shutting_down: std::sync::atomic::AtomicBool,
+    pub messages_in_flight: AtomicUsize,
     pub update_startup_lock: tokio::sync::Mutex<()>,
 
-    pub espeak_voices: FixedArray<FixedString<u8>>,
-    pub gtts_voices: BTreeMap<FixedString<u8>, FixedString<u8>>,
-    pub polly_voices: BTreeMap<FixedString<u8>, PollyVoice>,
-    pub gcloud_voices: BTreeMap<FixedString<u8>, BTreeMap<FixedString<u8>, GoogleGender>>,
-
-    pub translation_languages: BTreeMap<FixedString<u8>, FixedString<u8>>,
+    pub voice_lists: RwLock<Arc<VoiceLists>>,
 }
 
 impl std::fmt::Debug for Data {
@@ -280,13 +409,99 @@ impl std::fmt::Debug for Data {
 }
 
 impl Data {
+    /// The current voice lists, which may be swapped out by a refresh at any time.
+    pub fn voices(&self) -> Arc<VoiceLists> {
+        self.voice_lists.read().clone()
+    }
+
+    /// The current webhooks, which may be swapped out by a config reload at any time.
+    pub fn webhooks(&self) -> Arc<WebhookConfig> {
+        self.webhooks.read().clone()
+    }
+
+    pub fn premium_config(&self) -> Option<Arc<PremiumConfig>> {
+        self.premium_config.read().clone()
+    }
+
+    /// Leaves the voice channel in a guild, forgetting the voice session so it is not resumed.
+    pub async fn leave_vc(&self, guild_id: GuildId) -> Result<()> {
+        self.last_to_xsaid_tracker.remove(&guild_id);
+        voice_sessions::delete(&self.pool, guild_id).await?;
+        self.songbird.remove(guild_id).await?;
+        Ok(())
+    }
+
+    /// Finds the first configured backend that can generate audio for the mode.
+    pub fn tts_backend(&self, mode: TTSMode) -> Option<&dyn TtsBackend> {
+        self.tts_backends
+            .iter()
+            .find(|backend| backend.supports(mode))
+            .map(AsRef::as_ref)
+    }
+
+    pub fn speaking_rate_info(&self, mode: TTSMode) -> Option<SpeakingRateInfo> {
+        match self.tts_backend(mode) {
+            Some(backend) => backend.speaking_rate_info(mode),
+            None => mode.speaking_rate_info(),
+        }
+    }
+
+    /// The cache statistics of every database handler, combined.
+    pub fn db_cache_stats(&self) -> database::CacheStats {
+        self.guilds_db.stats()
+            + self.userinfo_db.stats()
+            + self.nickname_db.stats()
+            + self.user_voice_db.stats()
+            + self.guild_voice_db.stats()
+    }
+
+    #[tracing::instrument(skip_all, fields(mode = %request.mode, backend = tracing::field::Empty))]
+    pub async fn synthesise(&self, request: SynthesisRequest<'_>) -> Result<Option<Audio>> {
+        let mode = request.mode;
+        let Some(backend) = self.tts_backend(mode) else {
+            anyhow::bail!("No TTS backend is configured for {mode}");
+        };
+
+        tracing::Span::current().record("backend", backend.name());
+        let result = {
+            let _permit = self
+                .request_limiter
+                .acquire()
+                .instrument(tracing::info_span!("request_queue"))
+                .await;
+
+            let start = std::time::Instant::now();
+            let result = backend.synthesise(request).await;
+            METRICS
+                .tts_request_duration
+                .with_label_values(&[backend.name()])
+                .observe(start.elapsed().as_secs_f64());
+
+            result
+        };
+
+        // Errors caused by the user's settings should not count against the mode.
+        let is_user_error = result.as_ref().is_err_and(TTSServiceError::is_user_error);
+
+        if result.is_err() && !is_user_error {
+            METRICS
+                .tts_request_errors
+                .with_label_values(&[backend.name()])
+                .inc();
+        }
+
+        self.mode_health
+            .record(mode, result.is_ok() || is_user_error);
+        result
+    }
+
     pub async fn speaking_rate(&self, user_id: UserId, mode: TTSMode) -> Result<Cow<'static, str>> {
         let row = self.user_voice_db.get((user_id.into(), mode)).await?;
 
         Ok(match row.speaking_rate {
             Some(r) => Cow::Owned(r.to_string()),
             None => Cow::Borrowed(
-                mode.speaking_rate_info()
+                self.speaking_rate_info(mode)
                     .map(|info| info.default)
                     .unwrap_or("1.0"),
             ),
@@ -294,7 +509,7 @@ impl Data {
     }
 
     async fn fetch_patreon_info(&self, user_id: UserId) -> Result<Option<PremiumInfo>> {
-        if let Some(config) = &self.premium_config {
+        if let Some(config) = self.premium_config() {
             let mut url = config.patreon_service.clone();
             url.set_path(&aformat!("/members/{user_id}"));
 
@@ -322,7 +537,7 @@ impl Data {
         http: &serenity::Http,
         user_id: UserId,
     ) -> Result<Option<PremiumInfo>> {
-        let Some(premium_config) = &self.premium_config else {
+        let Some(premium_config) = self.premium_config() else {
             // Should not be reached, but return fake anyway to simplify self-hosting.
             return Ok(Some(PremiumInfo::fake()));
         };
@@ -397,6 +612,7 @@ impl Data {
         }
     }
 
+    #[tracing::instrument(skip_all)]
     pub async fn is_premium_simple(
         &self,
         http: &serenity::Http,
@@ -452,10 +668,23 @@ impl Data {
             }
         };
 
-        if self.config.gtts_disabled.load(Ordering::Relaxed) && mode == TTSMode::gTTS {
+        if let Some(kill_switch) = self.mode_kill_switches.get(&mode) {
+            mode = kill_switch.fallback;
+        }
+
+        // Self-hosted instances may only have a local engine, which only provides eSpeak.
+        if self.tts_backend(mode).is_none() {
             mode = TTSMode::eSpeak;
         }
 
+        let fallback = mode.fallback();
+        if self.mode_health.is_failing(mode)
+            && !self.mode_health.is_failing(fallback)
+            && self.tts_backend(fallback).is_some()
+        {
+            mode = fallback;
+        }
+
         if mode.is_premium() && !guild_is_premium {
             mode = TTSMode::default();
 
@@ -479,6 +708,17 @@ impl Data {
             }
         }
 
+        let voice = self.voice_for_mode(author_id, guild_id, mode).await?;
+        Ok((voice, mode))
+    }
+
+    /// Picks the user's voice for a mode, falling back to the server's voice then the default.
+    pub async fn voice_for_mode(
+        &self,
+        author_id: UserId,
+        guild_id: Option<GuildId>,
+        mode: TTSMode,
+    ) -> Result<Cow<'static, str>> {
         let user_voice_row = self.user_voice_db.get((author_id.into(), mode)).await?;
         let voice =
             // Get user voice for user mode
@@ -496,7 +736,7 @@ impl Data {
                 None
             }.unwrap_or_else(|| Cow::Borrowed(mode.default_voice()));
 
-        Ok((voice, mode))
+        Ok(voice)
     }
 }
 
@@ -524,76 +764,284 @@ impl SpeakingRateInfo {
     }
 }
 
-#[derive(IntoStaticStr, sqlx::Type, TypeSize, Debug, Default, Hash, PartialEq, Eq, Copy, Clone)]
+#[derive(TypeSize, Debug, Default, Hash, PartialEq, Eq, Copy, Clone)]
 #[allow(non_camel_case_types)]
-#[sqlx(rename_all = "lowercase")]
-#[sqlx(type_name = "ttsmode")]
 pub enum TTSMode {
     #[default]
     gTTS,
     Polly,
     eSpeak,
     gCloud,
+    OpenAI,
+    /// A mode declared at runtime, indexing into [`dynamic_modes::all`].
+    Dynamic(u8),
 }
 
 impl TTSMode {
-    pub const fn is_premium(self) -> bool {
+    pub const CORE: [Self; 5] = [
+        Self::gTTS,
+        Self::eSpeak,
+        Self::gCloud,
+        Self::Polly,
+        Self::OpenAI,
+    ];
+
+    /// Every mode, including those registered at runtime.
+    pub fn all() -> impl Iterator<Item = Self> {
+        let dynamic_count = dynamic_modes::all().len() as u8;
+        Self::CORE
+            .into_iter()
+            .chain((0..dynamic_count).map(Self::Dynamic))
+    }
+
+    pub fn is_premium(self) -> bool {
         match self {
             Self::gTTS | Self::eSpeak => false,
-            Self::Polly | Self::gCloud => true,
+            Self::Polly | Self::gCloud | Self::OpenAI => true,
+            Self::Dynamic(index) => dynamic_modes::get(index).premium,
         }
     }
 
-    pub const fn default_voice(self) -> &'static str {
+    pub fn default_voice(self) -> &'static str {
         match self {
             Self::gTTS => "en",
             Self::eSpeak => "en1",
             Self::Polly => "Brian",
             Self::gCloud => "en-US A",
+            Self::OpenAI => OpenAIBackend::default_voice(),
+            Self::Dynamic(index) => dynamic_modes::get(index).default_voice.as_str(),
+        }
+    }
+
+    /// The audio format to request from the backend.
+    ///
+    /// Ogg/Opus can be played by songbird without being transcoded, but not every mode
+    /// supports it, so the rest use mp3. Backends which reject the format are retried as mp3.
+    pub fn preferred_format(self) -> &'static str {
+        match self {
+            Self::gCloud | Self::OpenAI => "opus",
+            Self::gTTS | Self::eSpeak | Self::Polly => "mp3",
+            Self::Dynamic(index) => dynamic_modes::get(index).preferred_format.as_str(),
         }
     }
 
-    pub const fn speaking_rate_info(self) -> Option<SpeakingRateInfo> {
+    /// The mode to use instead, if this mode is failing.
+    ///
+    /// The premium modes often fail together, as they share the tts-service, so fall back
+    /// to a free mode instead of each other.
+    pub const fn fallback(self) -> Self {
+        match self {
+            Self::gTTS => Self::eSpeak,
+            Self::eSpeak | Self::Polly | Self::gCloud | Self::OpenAI | Self::Dynamic(_) => {
+                Self::gTTS
+            }
+        }
+    }
+
+    pub fn speaking_rate_info(self) -> Option<SpeakingRateInfo> {
         match self {
             Self::gTTS => None,
-            Self::gCloud => SpeakingRateInfo::new(0.25, "1.0", 4.0, "x"),
+            Self::gCloud | Self::OpenAI => SpeakingRateInfo::new(0.25, "1.0", 4.0, "x"),
             Self::Polly => SpeakingRateInfo::new(10.0, "100.0", 500.0, "%"),
             Self::eSpeak => SpeakingRateInfo::new(100.0, "175.0", 400.0, " words per minute"),
+            Self::Dynamic(index) => dynamic_modes::get(index).speaking_rate(),
         }
     }
-}
 
-into_static_display!(TTSMode, max_length(6));
+    /// The name shown when picking a mode in a slash command.
+    pub fn display_name(self) -> Cow<'static, str> {
+        Cow::Borrowed(match self {
+            Self::gTTS => "Google Translate TTS (female) (default)",
+            Self::eSpeak => "eSpeak TTS (male)",
+            Self::gCloud => "⭐ gCloud TTS (changeable) ⭐",
+            Self::Polly => "⭐ Amazon Polly TTS (changeable) ⭐",
+            Self::OpenAI => "⭐ OpenAI TTS (changeable) ⭐",
+            Self::Dynamic(index) => {
+                let mode = dynamic_modes::get(index);
+                if mode.premium {
+                    return Cow::Owned(format!("⭐ {} ⭐", mode.display_name));
+                }
 
-#[derive(poise::ChoiceParameter, Clone, Copy)]
-#[allow(non_camel_case_types)]
-pub enum TTSModeChoice {
-    // Name to show in slash command invoke               Aliases for prefix
-    #[name = "Google Translate TTS (female) (default)"]
-    #[name = "gtts"]
-    gTTS,
-    #[name = "eSpeak TTS (male)"]
-    #[name = "espeak"]
-    eSpeak,
-    #[name = "⭐ gCloud TTS (changeable) ⭐"]
-    #[name = "gcloud"]
-    gCloud,
-    #[name = "⭐ Amazon Polly TTS (changeable) ⭐"]
-    #[name = "polly"]
-    Polly,
+                mode.display_name.as_str()
+            }
+        })
+    }
+
+    /// The name of the mode in the `TTSMode` database enum.
+    pub fn db_name(self) -> &'static str {
+        match self {
+            Self::gTTS => "gtts",
+            Self::Polly => "polly",
+            Self::eSpeak => "espeak",
+            Self::gCloud => "gcloud",
+            Self::OpenAI => "openai",
+            Self::Dynamic(index) => dynamic_modes::get(index).name.as_str(),
+        }
+    }
+
+    fn from_db_name(name: &str) -> Self {
+        name.parse().unwrap_or_else(|_| {
+            // The mode has been removed from the tts-service, so reset to the default.
+            let default = Self::default();
+            warn!(
+                "Unknown TTS mode `{name}` loaded from the database, replacing it with {default}"
+            );
+            default
+        })
+    }
 }
 
-impl From<TTSModeChoice> for TTSMode {
-    fn from(mode: TTSModeChoice) -> Self {
+impl From<&TTSMode> for &'static str {
+    fn from(mode: &TTSMode) -> Self {
         match mode {
-            TTSModeChoice::gTTS => Self::gTTS,
-            TTSModeChoice::Polly => Self::Polly,
-            TTSModeChoice::eSpeak => Self::eSpeak,
-            TTSModeChoice::gCloud => Self::gCloud,
+            TTSMode::gTTS => "gTTS",
+            TTSMode::Polly => "Polly",
+            TTSMode::eSpeak => "eSpeak",
+            TTSMode::gCloud => "gCloud",
+            TTSMode::OpenAI => "OpenAI",
+            TTSMode::Dynamic(index) => dynamic_modes::get(*index).name.as_str(),
         }
     }
 }
 
+impl From<TTSMode> for &'static str {
+    fn from(mode: TTSMode) -> Self {
+        (&mode).into()
+    }
+}
+
+into_static_display!(TTSMode, max_length(32));
+
+#[derive(Debug)]
+pub struct UnknownModeError;
+
+impl std::fmt::Display for UnknownModeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Unknown TTS mode, please pick one of the suggested modes")
+    }
+}
+
+impl std::error::Error for UnknownModeError {}
+
+impl std::str::FromStr for TTSMode {
+    type Err = UnknownModeError;
+
+    /// Parses either the database name or display name of a mode, ignoring case.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::all()
+            .find(|mode| {
+                let display: &str = mode.into();
+                mode.db_name().eq_ignore_ascii_case(name) || display.eq_ignore_ascii_case(name)
+            })
+            .ok_or(UnknownModeError)
+    }
+}
+
+impl sqlx::Type<sqlx::Postgres> for TTSMode {
+    fn type_info() -> sqlx::postgres::PgTypeInfo {
+        sqlx::postgres::PgTypeInfo::with_name("ttsmode")
+    }
+}
+
+impl sqlx::postgres::PgHasArrayType for TTSMode {
+    fn array_type_info() -> sqlx::postgres::PgTypeInfo {
+        sqlx::postgres::PgTypeInfo::with_name("_ttsmode")
+    }
+}
+
+impl sqlx::Encode<'_, sqlx::Postgres> for TTSMode {
+    fn encode_by_ref(
+        &self,
+        buf: &mut sqlx::postgres::PgArgumentBuffer,
+    ) -> Result<sqlx::encode::IsNull, sqlx::error::BoxDynError> {
+        <&str as sqlx::Encode<sqlx::Postgres>>::encode(self.db_name(), buf)
+    }
+}
+
+impl<'r> sqlx::Decode<'r, sqlx::Postgres> for TTSMode {
+    fn decode(value: sqlx::postgres::PgValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        let name = <&str as sqlx::Decode<sqlx::Postgres>>::decode(value)?;
+        Ok(Self::from_db_name(name))
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl sqlx::Type<sqlx::Sqlite> for TTSMode {
+    fn type_info() -> sqlx::sqlite::SqliteTypeInfo {
+        <str as sqlx::Type<sqlx::Sqlite>>::type_info()
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl<'q> sqlx::Encode<'q, sqlx::Sqlite> for TTSMode {
+    fn encode_by_ref(
+        &self,
+        buf: &mut Vec<sqlx::sqlite::SqliteArgumentValue<'q>>,
+    ) -> Result<sqlx::encode::IsNull, sqlx::error::BoxDynError> {
+        <&str as sqlx::Encode<sqlx::Sqlite>>::encode(self.db_name(), buf)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl<'r> sqlx::Decode<'r, sqlx::Sqlite> for TTSMode {
+    fn decode(value: sqlx::sqlite::SqliteValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        let name = <&str as sqlx::Decode<sqlx::Sqlite>>::decode(value)?;
+        Ok(Self::from_db_name(name))
+    }
+}
+
+/// A list of modes, stored as a `TTSMode[]` in Postgres or a JSON array of names in `SQLite`.
+#[derive(Default)]
+pub struct ModeList(pub Vec<TTSMode>);
+
+impl sqlx::Type<sqlx::Postgres> for ModeList {
+    fn type_info() -> sqlx::postgres::PgTypeInfo {
+        <Vec<TTSMode> as sqlx::Type<sqlx::Postgres>>::type_info()
+    }
+}
+
+impl sqlx::Encode<'_, sqlx::Postgres> for ModeList {
+    fn encode_by_ref(
+        &self,
+        buf: &mut sqlx::postgres::PgArgumentBuffer,
+    ) -> Result<sqlx::encode::IsNull, sqlx::error::BoxDynError> {
+        <Vec<TTSMode> as sqlx::Encode<sqlx::Postgres>>::encode_by_ref(&self.0, buf)
+    }
+}
+
+impl<'r> sqlx::Decode<'r, sqlx::Postgres> for ModeList {
+    fn decode(value: sqlx::postgres::PgValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        <Vec<TTSMode> as sqlx::Decode<sqlx::Postgres>>::decode(value).map(Self)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl sqlx::Type<sqlx::Sqlite> for ModeList {
+    fn type_info() -> sqlx::sqlite::SqliteTypeInfo {
+        <str as sqlx::Type<sqlx::Sqlite>>::type_info()
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl<'q> sqlx::Encode<'q, sqlx::Sqlite> for ModeList {
+    fn encode_by_ref(
+        &self,
+        buf: &mut Vec<sqlx::sqlite::SqliteArgumentValue<'q>>,
+    ) -> Result<sqlx::encode::IsNull, sqlx::error::BoxDynError> {
+        let names: Vec<&str> = self.0.iter().map(|mode| mode.db_name()).collect();
+        <String as sqlx::Encode<sqlx::Sqlite>>::encode(serde_json::to_string(&names)?, buf)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl<'r> sqlx::Decode<'r, sqlx::Sqlite> for ModeList {
+    fn decode(value: sqlx::sqlite::SqliteValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        let raw = <&str as sqlx::Decode<sqlx::Sqlite>>::decode(value)?;
+        let names: Vec<&str> = serde_json::from_str(raw)?;
+        Ok(Self(names.into_iter().map(TTSMode::from_db_name).collect()))
+    }
+}
+
 #[derive(serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GoogleVoice {
@@ -643,7 +1091,7 @@ fn deserialize_error_code<'de, D: serde::Deserializer<'de>>(
     })
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum TTSServiceErrorCode {
     Unknown,
     UnknownVoice,
@@ -655,10 +1103,15 @@ impl TTSServiceErrorCode {
     pub const fn should_ignore(self) -> bool {
         matches!(self, Self::AudioTooLong)
     }
+
+    /// If the error was caused by the user's settings, instead of the backend failing.
+    pub const fn is_user_error(self) -> bool {
+        matches!(self, Self::UnknownVoice | Self::InvalidSpeakingRate)
+    }
 }
 
 #[must_use]
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, Debug)]
 pub struct TTSServiceError {
     pub display: String,
     #[serde(deserialize_with = "deserialize_error_code")]
@@ -671,6 +1124,18 @@ impl std::fmt::Display for TTSServiceError {
     }
 }
 
+impl std::error::Error for TTSServiceError {}
+
+impl TTSServiceError {
+    /// If `err` was caused by the user's settings, so should not count against the backend.
+    ///
+    /// The tts-service also reports its upstream failing with a 5xx and the `Unknown` code.
+    pub fn is_user_error(err: &Error) -> bool {
+        err.downcast_ref::<Self>()
+            .is_some_and(|err| err.code.is_user_error())
+    }
+}
+
 pub type Command = poise::Command<Data, CommandError>;
 pub type Context<'a> = poise::Context<'a, Data, CommandError>;
 pub type PrefixContext<'a> = poise::PrefixContext<'a, Data, CommandError>;
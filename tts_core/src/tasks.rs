@@ -0,0 +1,180 @@
+use std::{
+    collections::BTreeMap,
+    sync::LazyLock,
+    time::{Duration, SystemTime},
+};
+
+use parking_lot::Mutex;
+use rand::Rng as _;
+use tokio::sync::watch;
+
+/// Every background task of the process, as they are started before [`crate::structs::Data`].
+pub static TASKS: LazyLock<TaskRegistry> = LazyLock::new(TaskRegistry::new);
+
+/// How many runs in a row have to fail before a task is reported as failing.
+pub const ALERT_AFTER_FAILURES: u32 = 3;
+
+#[derive(Clone)]
+pub struct TaskStatus {
+    pub period: Duration,
+    pub running: bool,
+    pub stopped: bool,
+    pub runs: u64,
+    pub last_run: Option<SystemTime>,
+    pub last_success: Option<SystemTime>,
+    pub last_error: Option<String>,
+    pub consecutive_failures: u32,
+    pub next_run: Option<SystemTime>,
+}
+
+impl TaskStatus {
+    pub fn is_failing(&self) -> bool {
+        self.consecutive_failures >= ALERT_AFTER_FAILURES
+    }
+}
+
+#[derive(Default)]
+struct RunState {
+    cancelled: bool,
+    running: usize,
+}
+
+/// Held while a task is running, so shutdown can wait for it.
+pub struct RunGuard {
+    name: &'static str,
+}
+
+impl Drop for RunGuard {
+    fn drop(&mut self) {
+        TASKS.run_state.send_modify(|state| state.running -= 1);
+        if let Some(status) = TASKS.tasks.lock().get_mut(self.name) {
+            status.running = false;
+        }
+    }
+}
+
+pub struct TaskRegistry {
+    tasks: Mutex<BTreeMap<&'static str, TaskStatus>>,
+    run_state: watch::Sender<RunState>,
+}
+
+impl TaskRegistry {
+    fn new() -> Self {
+        Self {
+            tasks: Mutex::default(),
+            run_state: watch::channel(RunState::default()).0,
+        }
+    }
+
+    pub fn register(&self, name: &'static str, period: Duration) {
+        self.tasks.lock().insert(
+            name,
+            TaskStatus {
+                period,
+                running: false,
+                stopped: false,
+                runs: 0,
+                last_run: None,
+                last_success: None,
+                last_error: None,
+                consecutive_failures: 0,
+                next_run: Some(SystemTime::now()),
+            },
+        );
+    }
+
+    /// Marks the task as running, or returns [`None`] if the registry has been cancelled.
+    #[must_use]
+    pub fn start_run(&self, name: &'static str) -> Option<RunGuard> {
+        let started = self.run_state.send_if_modified(|state| {
+            if !state.cancelled {
+                state.running += 1;
+            }
+
+            !state.cancelled
+        });
+
+        if !started {
+            return None;
+        }
+
+        if let Some(status) = self.tasks.lock().get_mut(name) {
+            status.running = true;
+            status.runs += 1;
+            status.last_run = Some(SystemTime::now());
+            status.next_run = None;
+        }
+
+        Some(RunGuard { name })
+    }
+
+    /// Records the outcome of a run, returning how many runs in a row have failed before it.
+    pub fn finish_run(&self, name: &'static str, error: Option<String>) -> u32 {
+        let mut tasks = self.tasks.lock();
+        let Some(status) = tasks.get_mut(name) else {
+            return 0;
+        };
+
+        let previous_failures = status.consecutive_failures;
+        if error.is_some() {
+            status.consecutive_failures += 1;
+        } else {
+            status.consecutive_failures = 0;
+            status.last_success = Some(SystemTime::now());
+        }
+
+        status.last_error = error;
+        previous_failures
+    }
+
+    /// Picks how long after the start of the last run the next one starts, which is the period
+    /// plus up to a tenth of it.
+    ///
+    /// The jitter stops tasks with the same period from always running at the same time.
+    pub fn schedule_next(&self, name: &'static str, period: Duration) -> Duration {
+        let max_jitter = u64::try_from(period.as_millis() / 10).unwrap_or(u64::MAX);
+        let jitter = rand::thread_rng().gen_range(0..=max_jitter);
+        let delay = period + Duration::from_millis(jitter);
+
+        if let Some(status) = self.tasks.lock().get_mut(name) {
+            let last_run = status.last_run.unwrap_or_else(SystemTime::now);
+            status.next_run = Some(last_run + delay);
+        }
+
+        delay
+    }
+
+    pub fn stop(&self, name: &'static str) {
+        if let Some(status) = self.tasks.lock().get_mut(name) {
+            status.stopped = true;
+            status.next_run = None;
+        }
+    }
+
+    /// The status of every registered task, sorted by name.
+    pub fn snapshot(&self) -> Vec<(&'static str, TaskStatus)> {
+        let tasks = self.tasks.lock();
+        tasks
+            .iter()
+            .map(|(name, status)| (*name, status.clone()))
+            .collect()
+    }
+
+    /// Resolves once [`Self::cancel`] has been called.
+    pub async fn cancelled(&self) {
+        let mut run_state = self.run_state.subscribe();
+        // The sender is static, so this cannot fail.
+        let _ = run_state.wait_for(|state| state.cancelled).await;
+    }
+
+    /// Stops every task from running again, then waits for the current runs to finish.
+    ///
+    /// Returns `false` if some runs were still going after `timeout`.
+    pub async fn cancel(&self, timeout: Duration) -> bool {
+        self.run_state.send_modify(|state| state.cancelled = true);
+
+        let mut run_state = self.run_state.subscribe();
+        let finished = run_state.wait_for(|state| state.running == 0);
+        tokio::time::timeout(timeout, finished).await.is_ok()
+    }
+}
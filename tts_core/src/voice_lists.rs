@@ -0,0 +1,308 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use poise::serenity_prelude::small_fixed_array::{FixedArray, FixedString, TruncatingInto as _};
+use tracing::warn;
+
+use crate::{
+    backends::TtsBackend,
+    database, dynamic_modes,
+    structs::{Data, GoogleGender, GoogleVoice, PollyVoice, Result, TTSMode},
+};
+
+/// Every voice list, and the translation languages, fetched from the backends together.
+///
+/// This is swapped out as a whole when refreshed, see [`Data::voices`].
+#[derive(Default)]
+pub struct VoiceLists {
+    pub gtts_voices: BTreeMap<FixedString<u8>, FixedString<u8>>,
+    pub espeak_voices: FixedArray<FixedString<u8>>,
+    pub openai_voices: FixedArray<FixedString<u8>>,
+    /// The voices of each [`TTSMode::Dynamic`] mode, by index.
+    pub dynamic_voices: Vec<BTreeMap<FixedString<u8>, FixedString<u8>>>,
+    pub polly_voices: BTreeMap<FixedString<u8>, PollyVoice>,
+    pub gcloud_voices: BTreeMap<FixedString<u8>, BTreeMap<FixedString<u8>, GoogleGender>>,
+
+    pub translation_languages: BTreeMap<FixedString<u8>, FixedString<u8>>,
+}
+
+/// Voices that were removed by a refresh, and how many users or servers had them selected.
+pub struct RemovedVoices {
+    pub mode: TTSMode,
+    pub voices: Vec<String>,
+    pub affected_users: i64,
+    pub affected_guilds: i64,
+}
+
+async fn load_cached(pool: &database::Pool, kind: &str) -> Result<Option<serde_json::Value>> {
+    let row: Option<(String,)> =
+        sqlx::query_as("SELECT data FROM voice_list_cache WHERE kind = $1")
+            .bind(kind)
+            .fetch_optional(pool)
+            .await?;
+
+    Ok(row.map(|(data,)| serde_json::from_str(&data)).transpose()?)
+}
+
+async fn store_cached(pool: &database::Pool, kind: &str, raw: &serde_json::Value) -> Result<()> {
+    sqlx::query(
+        "
+        INSERT INTO voice_list_cache(kind, data, updated_at)
+        VALUES ($1, $2, CURRENT_TIMESTAMP)
+        ON CONFLICT (kind) DO UPDATE SET
+            data = EXCLUDED.data,
+            updated_at = EXCLUDED.updated_at
+    ",
+    )
+    .bind(kind)
+    .bind(raw.to_string())
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Caches a freshly fetched list, or loads the last cached list if fetching failed.
+///
+/// `Ok(None)` means no backend provides the list, so it is left empty.
+pub async fn fetch_or_cached(
+    pool: &database::Pool,
+    kind: &str,
+    fetched: Result<Option<serde_json::Value>>,
+) -> Result<Option<serde_json::Value>> {
+    match fetched {
+        Ok(Some(raw)) => {
+            store_cached(pool, kind, &raw).await?;
+            Ok(Some(raw))
+        }
+        Ok(None) => Ok(None),
+        Err(err) => {
+            let cached = load_cached(pool, kind).await?;
+            if cached.is_some() {
+                warn!("Failed to fetch {kind}, using cached copy: {err:?}");
+            } else {
+                warn!("Failed to fetch {kind}, and nothing is cached: {err:?}");
+            }
+
+            Ok(cached)
+        }
+    }
+}
+
+async fn load_voices<T: serde::de::DeserializeOwned + Default>(
+    pool: &database::Pool,
+    tts_backends: &[Box<dyn TtsBackend>],
+    mode: TTSMode,
+) -> Result<T> {
+    let raw = load_raw_voices(pool, tts_backends, mode).await?;
+    Ok(raw
+        .map(serde_json::from_value)
+        .transpose()?
+        .unwrap_or_default())
+}
+
+async fn load_raw_voices(
+    pool: &database::Pool,
+    tts_backends: &[Box<dyn TtsBackend>],
+    mode: TTSMode,
+) -> Result<Option<serde_json::Value>> {
+    let backend = tts_backends.iter().find(|backend| backend.supports(mode));
+    let fetched = match backend {
+        Some(backend) => backend.fetch_voices(mode).await.map(Some),
+        None => Ok(None),
+    };
+
+    let kind = format!("voices:{}", mode.db_name());
+    fetch_or_cached(pool, &kind, fetched).await
+}
+
+async fn load_translation_languages(
+    pool: &database::Pool,
+    tts_backends: &[Box<dyn TtsBackend>],
+) -> Result<BTreeMap<FixedString<u8>, FixedString<u8>>> {
+    let mut fetched = Ok(None);
+    for backend in tts_backends {
+        fetched = backend.fetch_translation_languages().await;
+        if !matches!(fetched, Ok(None)) {
+            break;
+        }
+    }
+
+    let Some(raw) = fetch_or_cached(pool, "translation_languages", fetched).await? else {
+        return Ok(BTreeMap::new());
+    };
+
+    let raw_langs: Vec<(String, FixedString<u8>)> = serde_json::from_value(raw)?;
+    let lang_map = raw_langs.into_iter().map(|(mut lang, name)| {
+        lang.make_ascii_lowercase();
+        (lang.trunc_into(), name)
+    });
+
+    Ok(lang_map.collect())
+}
+
+fn prepare_gcloud_voices(
+    raw_map: Vec<GoogleVoice>,
+) -> BTreeMap<FixedString<u8>, BTreeMap<FixedString<u8>, GoogleGender>> {
+    // {lang_accent: {variant: gender}}
+    let mut cleaned_map = BTreeMap::new();
+    for gvoice in raw_map {
+        let variant = gvoice
+            .name
+            .splitn(3, '-')
+            .nth(2)
+            .and_then(|mode_variant| mode_variant.split_once('-'))
+            .filter(|(mode, _)| *mode == "Standard")
+            .map(|(_, variant)| variant);
+
+        if let Some(variant) = variant {
+            let [language] = gvoice.language_codes;
+            cleaned_map
+                .entry(language)
+                .or_insert_with(BTreeMap::new)
+                .insert(FixedString::from_str_trunc(variant), gvoice.ssml_gender);
+        }
+    }
+
+    cleaned_map
+}
+
+impl VoiceLists {
+    /// Fetches every list from the backends, falling back to the cached lists for any that fail.
+    pub async fn fetch(
+        pool: &database::Pool,
+        tts_backends: &[Box<dyn TtsBackend>],
+    ) -> Result<Self> {
+        let dynamic_voices =
+            (0..=u8::MAX)
+                .zip(dynamic_modes::all())
+                .map(|(index, mode)| async move {
+                    let raw = load_raw_voices(pool, tts_backends, TTSMode::Dynamic(index)).await?;
+                    let Some(raw) = raw else {
+                        return Ok(BTreeMap::new());
+                    };
+
+                    dynamic_modes::parse_voices(mode.voice_list, raw)
+                });
+
+        let (
+            gtts_voices,
+            espeak_voices,
+            openai_voices,
+            dynamic_voices,
+            gcloud_voices,
+            polly_voices,
+            translation_languages,
+        ) = tokio::try_join!(
+            load_voices(pool, tts_backends, TTSMode::gTTS),
+            load_voices(pool, tts_backends, TTSMode::eSpeak),
+            load_voices(pool, tts_backends, TTSMode::OpenAI),
+            serenity::futures::future::try_join_all(dynamic_voices),
+            load_voices::<Vec<GoogleVoice>>(pool, tts_backends, TTSMode::gCloud),
+            load_voices::<Vec<PollyVoice>>(pool, tts_backends, TTSMode::Polly),
+            load_translation_languages(pool, tts_backends),
+        )?;
+
+        Ok(Self {
+            gtts_voices,
+            espeak_voices,
+            openai_voices,
+            dynamic_voices,
+            translation_languages,
+            gcloud_voices: prepare_gcloud_voices(gcloud_voices),
+            polly_voices: polly_voices
+                .into_iter()
+                .map(|v| (v.id.clone(), v))
+                .collect(),
+        })
+    }
+
+    /// Every voice code that can be selected for a mode.
+    #[must_use]
+    pub fn voice_codes(&self, mode: TTSMode) -> BTreeSet<String> {
+        match mode {
+            TTSMode::gTTS => self.gtts_voices.keys().map(ToString::to_string).collect(),
+            TTSMode::eSpeak => self.espeak_voices.iter().map(ToString::to_string).collect(),
+            TTSMode::OpenAI => self.openai_voices.iter().map(ToString::to_string).collect(),
+            TTSMode::Polly => self.polly_voices.keys().map(ToString::to_string).collect(),
+            TTSMode::gCloud => self
+                .gcloud_voices
+                .iter()
+                .flat_map(|(language, variants)| {
+                    variants
+                        .keys()
+                        .map(move |variant| format!("{language} {variant}"))
+                })
+                .collect(),
+            TTSMode::Dynamic(index) => self.dynamic_voices[usize::from(index)]
+                .keys()
+                .map(ToString::to_string)
+                .collect(),
+        }
+    }
+}
+
+async fn count_affected(
+    pool: &database::Pool,
+    table: &str,
+    mode: TTSMode,
+    voices: &[String],
+) -> Result<i64> {
+    let query = format!(
+        "SELECT count(*) FROM {table} WHERE mode = $1 AND voice IN ({})",
+        database::placeholders(2, voices.len())
+    );
+
+    let mut query = sqlx::query_as(&query).bind(mode);
+    for voice in voices {
+        query = query.bind(voice);
+    }
+
+    let (count,): (i64,) = query.fetch_one(pool).await?;
+
+    Ok(count)
+}
+
+/// Re-fetches every voice list, swapping them into [`Data`] and reporting any removed voices.
+pub async fn refresh(data: &Data) -> Result<Vec<RemovedVoices>> {
+    let new_lists = VoiceLists::fetch(&data.pool, &data.tts_backends).await?;
+    let old_lists = data.voices();
+
+    let mut removed_voices = Vec::new();
+    for mode in TTSMode::all() {
+        let new_codes = new_lists.voice_codes(mode);
+        if new_codes.is_empty() {
+            // The list could not be loaded at all, so don't report every voice as removed.
+            continue;
+        }
+
+        let voices: Vec<_> = old_lists
+            .voice_codes(mode)
+            .into_iter()
+            .filter(|code| !new_codes.contains(code))
+            .collect();
+
+        if voices.is_empty() {
+            continue;
+        }
+
+        let (affected_users, affected_guilds) = tokio::try_join!(
+            count_affected(&data.pool, "user_voice", mode, &voices),
+            count_affected(&data.pool, "guild_voice", mode, &voices),
+        )?;
+
+        warn!(
+            "{mode} voices have been removed: {}. Selected by {affected_users} users and {affected_guilds} servers.",
+            voices.join(", ")
+        );
+
+        removed_voices.push(RemovedVoices {
+            mode,
+            voices,
+            affected_users,
+            affected_guilds,
+        });
+    }
+
+    *data.voice_lists.write() = std::sync::Arc::new(new_lists);
+    Ok(removed_voices)
+}
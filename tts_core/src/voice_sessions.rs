@@ -0,0 +1,83 @@
+use poise::serenity_prelude::{ChannelId, GuildId};
+
+use crate::{database, structs::Result};
+
+#[derive(sqlx::FromRow)]
+struct VoiceSessionRow {
+    guild_id: i64,
+    voice_channel_id: i64,
+    text_channel_id: i64,
+}
+
+/// A voice connection that was active when the bot last saw it, used to rejoin after a restart.
+pub struct VoiceSession {
+    pub guild_id: GuildId,
+    pub voice_channel_id: ChannelId,
+    pub text_channel_id: ChannelId,
+}
+
+impl From<VoiceSessionRow> for VoiceSession {
+    fn from(row: VoiceSessionRow) -> Self {
+        Self {
+            guild_id: GuildId::new(row.guild_id as u64),
+            voice_channel_id: ChannelId::new(row.voice_channel_id as u64),
+            text_channel_id: ChannelId::new(row.text_channel_id as u64),
+        }
+    }
+}
+
+pub async fn save(
+    pool: &database::Pool,
+    guild_id: GuildId,
+    voice_channel_id: ChannelId,
+    text_channel_id: ChannelId,
+) -> Result<()> {
+    sqlx::query(
+        "
+        INSERT INTO voice_sessions(guild_id, voice_channel_id, text_channel_id)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (guild_id) DO UPDATE SET
+            voice_channel_id = EXCLUDED.voice_channel_id,
+            text_channel_id = EXCLUDED.text_channel_id
+    ",
+    )
+    .bind(guild_id.get() as i64)
+    .bind(voice_channel_id.get() as i64)
+    .bind(text_channel_id.get() as i64)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn delete(pool: &database::Pool, guild_id: GuildId) -> Result<()> {
+    sqlx::query("DELETE FROM voice_sessions WHERE guild_id = $1")
+        .bind(guild_id.get() as i64)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn fetch_for_guilds(
+    pool: &database::Pool,
+    guild_ids: &[GuildId],
+) -> Result<Vec<VoiceSession>> {
+    if guild_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let query = format!(
+        "SELECT * FROM voice_sessions WHERE guild_id IN ({})",
+        database::placeholders(1, guild_ids.len())
+    );
+
+    let mut query = sqlx::query_as(&query);
+    for guild_id in guild_ids {
+        query = query.bind(guild_id.get() as i64);
+    }
+
+    let rows: Vec<VoiceSessionRow> = query.fetch_all(pool).await?;
+
+    Ok(rows.into_iter().map(VoiceSession::from).collect())
+}
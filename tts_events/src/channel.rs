@@ -24,8 +24,7 @@ pub async fn channel_delete(
     let call_channel_id = guild_call_channel_id(&data.songbird, channel.guild_id).await;
     if call_channel_id == Some(channel.id) {
         // Ignore errors from leaving the channel, probably already left.
-        let _ = data.songbird.remove(channel.guild_id).await;
-        data.last_to_xsaid_tracker.remove(&channel.guild_id);
+        let _ = data.leave_vc(channel.guild_id).await;
     }
 
     Ok(())
@@ -36,6 +36,7 @@ pub async fn listen(framework_ctx: FrameworkContext<'_>, event: &Event) -> Resul
         Event::Message { new_message } => message(framework_ctx, new_message).await,
         Event::GuildCreate { guild, is_new } => guild_create(framework_ctx, guild, *is_new).await,
         Event::Ready { data_about_bot } => ready(framework_ctx, data_about_bot).await,
+        Event::CacheReady { guilds } => cache_ready(framework_ctx, guilds).await,
         Event::GuildDelete { incomplete, full } => {
             guild_delete(framework_ctx, incomplete, full.as_ref()).await
         }
@@ -9,7 +9,6 @@ use poise::serenity_prelude as serenity;
 use tts_core::{
     common::{dm_generic, random_footer},
     constants::DM_WELCOME_MESSAGE,
-    opt_ext::OptionTryUnwrap,
     structs::{Data, FrameworkContext, Result},
 };
 
@@ -123,7 +122,9 @@ async fn process_support_dm(
             channel.say(&ctx.http, content).await?;
         } else if content.as_str() == "help" {
             channel.say(&ctx.http, "We cannot help you unless you ask a question, if you want the help command just do `-help`!").await?;
-        } else if !userinfo.dm_blocked() {
+        } else if !userinfo.dm_blocked()
+            && let Some(dm_logs) = data.webhooks().dm_logs.clone()
+        {
             let webhook_username = {
                 let mut tag = message.author.tag();
                 tag.push_str(&aformat!(" ({})", message.author.id));
@@ -156,10 +157,7 @@ async fn process_support_dm(
                         .collect::<Vec<_>>(),
                 );
 
-            data.webhooks
-                .dm_logs
-                .execute(&ctx.http, false, builder)
-                .await?;
+            dm_logs.execute(&ctx.http, false, builder).await?;
         }
     } else {
         let (client_id, title) = {
@@ -207,7 +205,12 @@ async fn process_support_response(
     data: &Data,
     channel: serenity::GuildChannel,
 ) -> Result<()> {
-    if data.webhooks.dm_logs.channel_id.try_unwrap()? != channel.id {
+    let webhooks = data.webhooks();
+    let dm_logs_channel = webhooks
+        .dm_logs
+        .as_ref()
+        .and_then(|dm_logs| dm_logs.channel_id);
+    if dm_logs_channel != Some(channel.id) {
         return Ok(());
     };
 
@@ -1,17 +1,25 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, future::Future};
 
 use aformat::ToArrayString as _;
 use poise::serenity_prelude as serenity;
+use tracing::{field::Empty, Instrument as _};
 
 use tts_core::{
-    common::{clean_msg, fetch_audio, prepare_url},
+    backends::SynthesisRequest,
+    common::{audio_to_input, clean_msg},
     database::{GuildRow, UserRow},
     errors,
+    metrics::METRICS,
     opt_ext::OptionTryUnwrap as _,
-    structs::{FrameworkContext, IsPremium, JoinVCToken, Result, TTSMode},
+    structs::{
+        Data, FrameworkContext, InFlightMessage, IsPremium, JoinVCToken, Result, TTSMode,
+        TTSServiceError, TTSServiceErrorCode,
+    },
     traits::SongbirdManagerExt as _,
+    voice_sessions,
 };
 
+#[tracing::instrument(skip_all, fields(guild_id = Empty, mode = Empty, message_length = Empty))]
 pub(crate) async fn process_tts_msg(
     framework_ctx: FrameworkContext<'_>,
     message: &serenity::Message,
@@ -23,6 +31,13 @@ pub(crate) async fn process_tts_msg(
         return Ok(());
     };
 
+    tracing::Span::current().record("guild_id", guild_id.get());
+
+    // Stop accepting new messages, so the queues can drain before shutdown.
+    let Some(_in_flight) = InFlightMessage::start(&data) else {
+        return Ok(());
+    };
+
     let (guild_row, user_row) = tokio::try_join!(
         data.guilds_db.get(guild_id.into()),
         data.userinfo_db.get(message.author.id.into()),
@@ -33,7 +48,7 @@ pub(crate) async fn process_tts_msg(
     };
 
     let is_premium = data.is_premium_simple(&ctx.http, guild_id).await?;
-    let (voice, mode) = {
+    let (mut voice, mut mode) = {
         if let Some(channel_id) = to_autojoin {
             let join_vc_lock = JoinVCToken::acquire(&data, guild_id);
             match data.songbird.join_vc(join_vc_lock, channel_id).await {
@@ -41,6 +56,8 @@ pub(crate) async fn process_tts_msg(
                 Err(songbird::error::JoinError::TimedOut) => return Ok(()),
                 Err(err) => return Err(err.into()),
             };
+
+            voice_sessions::save(&data.pool, guild_id, channel_id, message.channel_id).await?;
         }
 
         let is_ephemeral = message
@@ -93,22 +110,19 @@ pub(crate) async fn process_tts_msg(
         return Ok(());
     }
 
-    let speaking_rate = data.speaking_rate(message.author.id, mode).await?;
-    let url = prepare_url(
-        data.config.tts_service.clone(),
-        &content,
-        &voice,
-        mode,
-        &speaking_rate,
-        &guild_row.msg_length.to_arraystring(),
-        guild_row.target_lang(IsPremium::from(is_premium)),
-    );
+    let span = tracing::Span::current();
+    span.record("mode", mode.db_name());
+    span.record("message_length", content.len());
+
+    let mut speaking_rate = data.speaking_rate(message.author.id, mode).await?;
+    let max_length = guild_row.msg_length.to_arraystring();
+    let translation_lang = guild_row.target_lang(IsPremium::from(is_premium));
 
     let call_lock = if let Some(call) = data.songbird.get(guild_id) {
         call
     } else {
         // At this point, the bot is "in" the voice channel, but without a voice client,
-        // this is usually if the bot restarted but the bot is still in the vc from the last boot.
+        // this is usually if the bot restarted and the voice session could not be resumed.
         let voice_channel_id = {
             let guild = ctx.cache.guild(guild_id).try_unwrap()?;
             guild
@@ -119,48 +133,109 @@ pub(crate) async fn process_tts_msg(
         };
 
         let join_vc_token = JoinVCToken::acquire(&data, guild_id);
-        match data.songbird.join_vc(join_vc_token, voice_channel_id).await {
+        let call = match data.songbird.join_vc(join_vc_token, voice_channel_id).await {
             Ok(call) => call,
             Err(songbird::error::JoinError::TimedOut) => return Ok(()),
             Err(err) => return Err(err.into()),
-        }
-    };
+        };
 
-    // Pre-fetch the audio to handle max_length errors
-    let tts_auth_key = data.config.tts_service_auth_key.as_deref();
-    let Some(audio) = fetch_audio(&data.reqwest, url.clone(), tts_auth_key).await? else {
-        return Ok(());
+        voice_sessions::save(&data.pool, guild_id, voice_channel_id, message.channel_id).await?;
+        call
     };
 
-    let hint = audio
-        .headers()
-        .get(reqwest::header::CONTENT_TYPE)
-        .map(|ct| {
-            let mut hint = songbird::input::core::probe::Hint::new();
-            hint.mime_type(ct.to_str()?);
-            Ok::<_, anyhow::Error>(hint)
+    // Pre-fetch the audio to handle max_length errors
+    let mut audio = data
+        .synthesise(SynthesisRequest {
+            mode,
+            text: &content,
+            voice: &voice,
+            speaking_rate: &speaking_rate,
+            max_length: &max_length,
+            translation_lang,
         })
-        .transpose()?;
+        .await;
+
+    if let Err(err) = &audio
+        && let Some(service_err) = err.downcast_ref::<TTSServiceError>()
+        && reset_invalid_setting(ctx, &data, message, guild_id, mode, service_err.code).await?
+    {
+        // Retry once, now the setting has been reset to the default.
+        (voice, mode) = data
+            .parse_user_or_guild_with_premium(message.author.id, Some((guild_id, is_premium)))
+            .await?;
+        speaking_rate = data.speaking_rate(message.author.id, mode).await?;
+
+        audio = data
+            .synthesise(SynthesisRequest {
+                mode,
+                text: &content,
+                voice: &voice,
+                speaking_rate: &speaking_rate,
+                max_length: &max_length,
+                translation_lang,
+            })
+            .await;
+    }
+
+    // The backend failed, rather than the user's settings, so try the server's fallback modes.
+    if should_fall_back(&audio) {
+        let failed_mode = mode;
+        let (data, content, max_length) = (&data, &content, &max_length);
+        let fallback = walk_fallback_chain(
+            guild_row.fallback_modes(failed_mode),
+            |fallback| async move {
+                if (fallback.is_premium() && !is_premium)
+                    || data.tts_backend(fallback).is_none()
+                    || data.mode_kill_switches.contains_key(&fallback)
+                {
+                    return Ok(None);
+                }
+
+                let fallback_voice = data
+                    .voice_for_mode(message.author.id, Some(guild_id), fallback)
+                    .await?;
+                let fallback_speaking_rate =
+                    data.speaking_rate(message.author.id, fallback).await?;
+
+                let fallback_audio = data
+                    .synthesise(SynthesisRequest {
+                        mode: fallback,
+                        text: content,
+                        voice: &fallback_voice,
+                        speaking_rate: &fallback_speaking_rate,
+                        max_length,
+                        translation_lang,
+                    })
+                    .await;
+
+                Ok(fallback_audio.ok().map(|audio| (fallback_voice, audio)))
+            },
+        )
+        .await?;
+
+        if let Some((fallback, (fallback_voice, fallback_audio))) = fallback {
+            tracing::warn!("Guild {guild_id}: {failed_mode} failed, fell back to {fallback}");
+            data.analytics
+                .log(Cow::Owned(format!("{failed_mode}_fallback")), false);
+
+            (voice, mode, audio) = (fallback_voice, fallback, Ok(fallback_audio));
+        }
+    }
 
-    let input = Box::new(std::io::Cursor::new(audio.bytes().await?));
-    let wrapped_audio =
-        songbird::input::LiveInput::Raw(songbird::input::AudioStream { input, hint });
+    let Some(audio) = audio? else {
+        return Ok(());
+    };
 
+    let input = audio_to_input(audio);
     let track_handle = {
         let mut call = call_lock.lock().await;
-        call.enqueue_input(songbird::input::Input::Live(wrapped_audio, None))
+        call.enqueue_input(input)
+            .instrument(tracing::info_span!("enqueue"))
             .await
     };
 
-    data.analytics.log(
-        Cow::Borrowed(match mode {
-            TTSMode::gTTS => "gTTS_tts",
-            TTSMode::eSpeak => "eSpeak_tts",
-            TTSMode::gCloud => "gCloud_tts",
-            TTSMode::Polly => "Polly_tts",
-        }),
-        false,
-    );
+    data.analytics.log(Cow::Owned(format!("{mode}_tts")), false);
+    METRICS.messages.with_label_values(&[mode.db_name()]).inc();
 
     let guild = ctx.cache.guild(guild_id).try_unwrap()?;
     let (blank_name, blank_value, blank_inline) = errors::blank_field();
@@ -193,6 +268,98 @@ pub(crate) async fn process_tts_msg(
     .map_err(Into::into)
 }
 
+/// If synthesis failed because of the backend, rather than the user's settings.
+fn should_fall_back<T>(audio: &Result<T>) -> bool {
+    audio
+        .as_ref()
+        .is_err_and(|err| !TTSServiceError::is_user_error(err))
+}
+
+/// Tries each mode of a fallback chain in order, returning the first to produce a value.
+///
+/// `try_mode` returns [`None`] if the mode cannot be used or failed, to move on to the next one.
+async fn walk_fallback_chain<T, Fut>(
+    chain: impl IntoIterator<Item = TTSMode>,
+    mut try_mode: impl FnMut(TTSMode) -> Fut,
+) -> Result<Option<(TTSMode, T)>>
+where
+    Fut: Future<Output = Result<Option<T>>>,
+{
+    for mode in chain {
+        if let Some(value) = try_mode(mode).await? {
+            return Ok(Some((mode, value)));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Resets the setting the tts-service rejected back to the default, and tells the user.
+///
+/// Returns `false` if the error was not caused by a setting which can be reset.
+async fn reset_invalid_setting(
+    ctx: &serenity::Context,
+    data: &Data,
+    message: &serenity::Message,
+    guild_id: serenity::GuildId,
+    mode: TTSMode,
+    code: TTSServiceErrorCode,
+) -> Result<bool> {
+    let author_id = message.author.id;
+    let user_voice_key = (author_id.into(), mode);
+    let user_voice_row = data.user_voice_db.get(user_voice_key).await?;
+
+    let setting = match code {
+        // The voice is picked in the same order as `parse_user_or_guild_with_premium`.
+        TTSServiceErrorCode::UnknownVoice if user_voice_row.user_id.is_some() => {
+            if user_voice_row.voice.is_none() {
+                return Ok(false);
+            }
+
+            data.user_voice_db
+                .set_one(user_voice_key, "voice", None::<&str>)
+                .await?;
+            "your voice"
+        }
+        TTSServiceErrorCode::UnknownVoice => {
+            let guild_voice_key = (guild_id.into(), mode);
+            let guild_voice_row = data.guild_voice_db.get(guild_voice_key).await?;
+            if guild_voice_row.guild_id.is_none() {
+                return Ok(false);
+            }
+
+            data.guild_voice_db.delete(guild_voice_key).await?;
+            "this server's voice"
+        }
+        TTSServiceErrorCode::InvalidSpeakingRate => {
+            if user_voice_row.speaking_rate.is_none() {
+                return Ok(false);
+            }
+
+            data.user_voice_db
+                .set_one(user_voice_key, "speaking_rate", None::<f32>)
+                .await?;
+            "your speaking rate"
+        }
+        TTSServiceErrorCode::AudioTooLong | TTSServiceErrorCode::Unknown => return Ok(false),
+    };
+
+    tracing::warn!("Guild {guild_id} - User {author_id}: Reset {setting} for {mode}, as it was rejected by the tts-service");
+
+    let notice = format!(
+        "The {mode} voice mode no longer supports {setting}, so it has been reset to the default. You can change it again with `/set`.",
+    );
+
+    // Fall back to a reaction if the user has their DMs closed.
+    let dm = serenity::CreateMessage::default().content(notice);
+    if author_id.dm(&ctx.http, dm).await.is_err() {
+        message.react(&ctx.http, '⚠').await?;
+    }
+
+    Ok(true)
+}
+
+#[tracing::instrument(skip_all)]
 fn run_checks(
     ctx: &serenity::Context,
     message: &serenity::Message,
@@ -308,3 +475,46 @@ fn run_checks(
 
     Ok(Some((content, to_autojoin)))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// What the tts-service responds with, alongside a 5xx, when its upstream provider fails.
+    fn upstream_failure() -> Result<()> {
+        let err: TTSServiceError =
+            serde_json::from_str(r#"{"display": "Upstream provider failed", "code": 0}"#).unwrap();
+
+        Err(anyhow::Error::new(err).context("Error fetching audio"))
+    }
+
+    #[tokio::test]
+    async fn upstream_failure_walks_fallback_chain() {
+        assert!(should_fall_back(&upstream_failure()));
+
+        let mut tried = Vec::new();
+        let chain = [TTSMode::Polly, TTSMode::gCloud, TTSMode::eSpeak];
+        let fallback = walk_fallback_chain(chain, |mode| {
+            tried.push(mode);
+            let result = match mode {
+                TTSMode::gCloud => Ok(Some("audio")),
+                _ => Ok(None),
+            };
+
+            std::future::ready(result)
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(fallback, Some((TTSMode::gCloud, "audio")));
+        assert_eq!(tried, [TTSMode::Polly, TTSMode::gCloud]);
+    }
+
+    #[test]
+    fn user_errors_do_not_fall_back() {
+        let err: TTSServiceError =
+            serde_json::from_str(r#"{"display": "Unknown voice", "code": 1}"#).unwrap();
+
+        assert!(!should_fall_back::<()>(&Err(err.into())));
+    }
+}
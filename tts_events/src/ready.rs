@@ -1,4 +1,9 @@
-use std::{collections::HashMap, fmt::Write, num::NonZeroU16, sync::atomic::Ordering};
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    num::NonZeroU16,
+    sync::{atomic::Ordering, Arc},
+};
 
 use aformat::aformat;
 
@@ -7,7 +12,9 @@ use poise::serenity_prelude as serenity;
 
 use tts_core::{
     constants::FREE_NEUTRAL_COLOUR,
-    structs::{Data, FrameworkContext, Result},
+    structs::{Data, FrameworkContext, JoinVCToken, Result},
+    traits::SongbirdManagerExt as _,
+    voice_sessions::{self, VoiceSession},
 };
 use tts_tasks::Looper;
 
@@ -68,40 +75,29 @@ async fn update_startup_message(
             .title(title),
     );
 
-    data.webhooks
-        .logs
-        .edit_message(&ctx.http, data.startup_message, builder)
-        .await?;
+    let webhooks = data.webhooks();
+    if let Some(logs) = &webhooks.logs
+        && let Some(startup_message) = data.startup_message
+    {
+        logs.edit_message(&ctx.http, startup_message, builder)
+            .await?;
+    }
 
     Ok(())
 }
 
 #[cold]
-fn finalize_startup(ctx: &serenity::Context, data: &Data) {
-    if let Some(bot_list_tokens) = data.bot_list_tokens.lock().take() {
-        let stats_updater = tts_tasks::bot_list_updater::BotListUpdater::new(
-            data.reqwest.clone(),
-            ctx.cache.clone(),
-            bot_list_tokens,
-        );
-
-        tokio::spawn(stats_updater.start());
-    }
-
-    if let Some(website_info) = data.website_info.lock().take() {
-        let premium_config = data.premium_config.as_ref();
-        let patreon_service = premium_config.map(|c| c.patreon_service.clone());
-
-        let web_updater = tts_tasks::web_updater::Updater {
-            reqwest: data.reqwest.clone(),
-            cache: ctx.cache.clone(),
-            pool: data.pool.clone(),
-            config: website_info,
-            patreon_service,
-        };
-
-        tokio::spawn(web_updater.start());
-    }
+fn finalize_startup(ctx: &serenity::Context, data: &Arc<Data>) {
+    // These are started even if not configured, as a config reload may set them later.
+    let stats_updater =
+        tts_tasks::bot_list_updater::BotListUpdater::new(ctx.cache.clone(), data.clone());
+    tokio::spawn(stats_updater.start());
+
+    let web_updater = tts_tasks::web_updater::Updater {
+        cache: ctx.cache.clone(),
+        data: data.clone(),
+    };
+    tokio::spawn(web_updater.start());
 
     // Tell glibc to let go of the memory it's holding onto.
     // We are very unlikely to reach the peak of memory allocation that was just hit.
@@ -136,3 +132,111 @@ pub async fn ready(
 
     Ok(())
 }
+
+/// If the voice session should be dropped instead of resumed, as nobody is left to listen.
+fn is_session_stale(ctx: &serenity::Context, session: &VoiceSession) -> bool {
+    let bot_id = ctx.cache.current_user().id;
+    let Some(guild) = ctx.cache.guild(session.guild_id) else {
+        return true;
+    };
+
+    if !guild.channels.contains_key(&session.voice_channel_id) {
+        return true;
+    }
+
+    !guild
+        .voice_states
+        .iter()
+        .filter(|vs| vs.channel_id == Some(session.voice_channel_id) && vs.user_id != bot_id)
+        .any(|vs| {
+            // Members may not be cached, so anyone not known to be a bot counts as listening.
+            let is_bot = match &vs.member {
+                Some(member) => Some(member.user.bot()),
+                None => guild
+                    .members
+                    .get(&vs.user_id)
+                    .map(|member| member.user.bot()),
+            };
+
+            is_bot != Some(true)
+        })
+}
+
+async fn resume_voice_session(
+    ctx: &serenity::Context,
+    data: &Data,
+    session: VoiceSession,
+) -> Result<bool> {
+    let guild_id = session.guild_id;
+    if data.songbird.get(guild_id).is_some() {
+        return Ok(true);
+    }
+
+    if is_session_stale(ctx, &session) {
+        voice_sessions::delete(&data.pool, guild_id).await?;
+        return Ok(false);
+    }
+
+    let join_vc_token = JoinVCToken::acquire(data, guild_id);
+    if let Err(err) = data
+        .songbird
+        .join_vc(join_vc_token, session.voice_channel_id)
+        .await
+    {
+        tracing::warn!("Failed to resume voice session in {guild_id}: {err}");
+        voice_sessions::delete(&data.pool, guild_id).await?;
+
+        // Let the channel being read out know, instead of going silent without a reason.
+        let notice = aformat!(
+            "I could not rejoin <#{}> after restarting, please use `/join` to start reading again.",
+            session.voice_channel_id
+        );
+
+        if let Err(err) = session
+            .text_channel_id
+            .say(&ctx.http, notice.as_str())
+            .await
+        {
+            tracing::warn!("Failed to send resume notice in {guild_id}: {err}");
+        }
+
+        return Ok(false);
+    }
+
+    Ok(true)
+}
+
+pub async fn cache_ready(
+    framework_ctx: FrameworkContext<'_>,
+    guilds: &[serenity::GuildId],
+) -> Result<()> {
+    let data = framework_ctx.user_data();
+    let ctx = framework_ctx.serenity_context;
+
+    let sessions = voice_sessions::fetch_for_guilds(&data.pool, guilds).await?;
+    if sessions.is_empty() {
+        return Ok(());
+    }
+
+    let session_count = sessions.len();
+    let results = serenity::futures::future::join_all(
+        sessions
+            .into_iter()
+            .map(|session| resume_voice_session(ctx, &data, session)),
+    )
+    .await;
+
+    let mut resumed = 0;
+    for result in results {
+        if result? {
+            resumed += 1;
+        }
+    }
+
+    tracing::info!(
+        "Shard {}: Resumed {resumed}/{session_count} voice sessions",
+        ctx.shard_id.0
+    );
+
+    Ok(())
+}
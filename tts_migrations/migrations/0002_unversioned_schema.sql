@@ -0,0 +1,70 @@
+-- The schema changes made before migrations were versioned, kept idempotent as databases
+-- created before then may have already applied any of them.
+
+DO $$ BEGIN
+    CREATE type TTSMode AS ENUM (
+        'gtts',
+        'espeak',
+        'premium'
+    );
+
+    ALTER TYPE TTSMode RENAME VALUE 'premium' TO 'gcloud';
+    ALTER TYPE TTSMode ADD VALUE 'polly';
+EXCEPTION
+    WHEN OTHERS THEN null;
+END $$;
+
+CREATE TABLE IF NOT EXISTS guild_voice (
+    guild_id      bigint,
+    mode          TTSMode,
+    voice         text     NOT NULL,
+
+    PRIMARY KEY (guild_id, mode),
+
+    FOREIGN KEY       (guild_id)
+    REFERENCES guilds (guild_id)
+    ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS user_voice (
+    user_id       bigint,
+    mode          TTSMode,
+    voice         text,
+
+    PRIMARY KEY (user_id, mode),
+
+    FOREIGN KEY         (user_id)
+    REFERENCES userinfo (user_id)
+    ON DELETE CASCADE
+);
+
+ALTER TABLE userinfo
+    ADD COLUMN IF NOT EXISTS voice_mode          TTSMode,
+    ADD COLUMN IF NOT EXISTS premium_voice_mode  TTSMode,
+    ADD COLUMN IF NOT EXISTS bot_banned          bool     DEFAULT False,
+    ADD COLUMN IF NOT EXISTS use_new_formatting  bool     DEFAULT False;
+ALTER TABLE guilds
+    ADD COLUMN IF NOT EXISTS audience_ignore  bool       DEFAULT True,
+    ADD COLUMN IF NOT EXISTS voice_mode       TTSMode    DEFAULT 'gtts',
+    ADD COLUMN IF NOT EXISTS to_translate     bool       DEFAULT False,
+    ADD COLUMN IF NOT EXISTS target_lang      varchar(5),
+    ADD COLUMN IF NOT EXISTS premium_user     bigint,
+    ADD COLUMN IF NOT EXISTS require_voice    bool       DEFAULT True,
+    ADD COLUMN IF NOT EXISTS required_role    bigint,
+    ADD COLUMN IF NOT EXISTS required_prefix  varchar(6),
+    ADD COLUMN IF NOT EXISTS text_in_voice    bool       DEFAULT True,
+    ADD COLUMN IF NOT EXISTS skip_emoji       bool       DEFAULT False;
+ALTER TABLE user_voice
+    ADD COLUMN IF NOT EXISTS speaking_rate real;
+
+-- The old table had a pkey on traceback, now we hash and pkey on that
+ALTER TABLE errors
+    ADD COLUMN IF NOT EXISTS traceback_hash bytea;
+DELETE FROM errors WHERE traceback_hash IS NULL;
+ALTER TABLE errors
+    DROP CONSTRAINT IF EXISTS errors_pkey,
+    DROP CONSTRAINT IF EXISTS traceback_hash_pkey,
+    ADD CONSTRAINT traceback_hash_pkey PRIMARY KEY (traceback_hash);
+
+INSERT INTO user_voice  (user_id, mode)         VALUES(0, 'gtts')       ON CONFLICT (user_id, mode)  DO NOTHING;
+INSERT INTO guild_voice (guild_id, mode, voice) VALUES(0, 'gtts', 'en') ON CONFLICT (guild_id, mode) DO NOTHING;
@@ -0,0 +1,6 @@
+-- The voice channels the bot is in, so they can be rejoined after a restart
+CREATE TABLE voice_sessions (
+    guild_id          bigint  PRIMARY KEY,
+    voice_channel_id  bigint  NOT NULL,
+    text_channel_id   bigint  NOT NULL
+);
@@ -0,0 +1,5 @@
+CREATE TABLE mode_kill_switches (
+    mode      TTSMode  PRIMARY KEY,
+    fallback  TTSMode  NOT NULL,
+    reason    text     NOT NULL
+);
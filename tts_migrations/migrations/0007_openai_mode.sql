@@ -0,0 +1,3 @@
+-- Adding an enum value in a transaction requires PostgreSQL 12 or newer, and the value
+-- cannot be used by a later migration until this transaction has committed.
+ALTER TYPE TTSMode ADD VALUE IF NOT EXISTS 'openai';
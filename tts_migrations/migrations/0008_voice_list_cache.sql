@@ -0,0 +1,5 @@
+CREATE TABLE voice_list_cache (
+    kind        text       PRIMARY KEY,
+    data        text       NOT NULL,
+    updated_at  timestamp  NOT NULL
+);
@@ -0,0 +1,2 @@
+ALTER TABLE guilds
+    ADD COLUMN fallback_modes TTSMode[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,28 @@
+-- Tells every bot process to drop its cached copy of a row when it is changed
+CREATE OR REPLACE FUNCTION notify_cache_invalidation() RETURNS trigger AS $$
+DECLARE
+    changed jsonb := to_jsonb(COALESCE(NEW, OLD));
+BEGIN
+    PERFORM pg_notify('cache_invalidation', json_build_object(
+        'table', TG_TABLE_NAME,
+        'keys', json_build_array(changed -> TG_ARGV[0], changed -> TG_ARGV[1])
+    )::text);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS cache_invalidation ON guilds;
+CREATE TRIGGER cache_invalidation AFTER INSERT OR UPDATE OR DELETE ON guilds
+    FOR EACH ROW EXECUTE FUNCTION notify_cache_invalidation('guild_id');
+DROP TRIGGER IF EXISTS cache_invalidation ON userinfo;
+CREATE TRIGGER cache_invalidation AFTER INSERT OR UPDATE OR DELETE ON userinfo
+    FOR EACH ROW EXECUTE FUNCTION notify_cache_invalidation('user_id');
+DROP TRIGGER IF EXISTS cache_invalidation ON nicknames;
+CREATE TRIGGER cache_invalidation AFTER INSERT OR UPDATE OR DELETE ON nicknames
+    FOR EACH ROW EXECUTE FUNCTION notify_cache_invalidation('guild_id', 'user_id');
+DROP TRIGGER IF EXISTS cache_invalidation ON user_voice;
+CREATE TRIGGER cache_invalidation AFTER INSERT OR UPDATE OR DELETE ON user_voice
+    FOR EACH ROW EXECUTE FUNCTION notify_cache_invalidation('user_id', 'mode');
+DROP TRIGGER IF EXISTS cache_invalidation ON guild_voice;
+CREATE TRIGGER cache_invalidation AFTER INSERT OR UPDATE OR DELETE ON guild_voice
+    FOR EACH ROW EXECUTE FUNCTION notify_cache_invalidation('guild_id', 'mode');
@@ -0,0 +1,161 @@
+use serde::de::{self, IntoDeserializer as _, Visitor};
+
+/// Deserializes a config value, parsing strings as TOML where the field expects another type.
+///
+/// Environment overrides are inserted as strings, as only the field they end up in knows if
+/// `1234` is a password or a port.
+pub(crate) struct Lenient(pub toml::Value);
+
+impl Lenient {
+    /// Parses a string as the TOML value it contains, such as `1234` or `["a", "b"]`.
+    fn parse(self) -> Result<toml::Value, toml::de::Error> {
+        let toml::Value::String(raw) = self.0 else {
+            return Ok(self.0);
+        };
+
+        let parsed = format!("value = {raw}").parse::<toml::Table>();
+        parsed
+            .ok()
+            .and_then(|mut parsed| parsed.remove("value"))
+            .ok_or_else(|| de::Error::custom(format!("`{raw}` is not a valid value")))
+    }
+}
+
+/// Implements methods for types which are never strings, so a string is parsed first.
+macro_rules! parse_then_any {
+    ($($method:ident),*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+            Self(self.parse()?).deserialize_any(visitor)
+        }
+    )*};
+}
+
+impl<'de> de::Deserializer<'de> for Lenient {
+    type Error = toml::de::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            toml::Value::Table(table) => visitor.visit_map(LenientMap {
+                iter: table.into_iter(),
+                value: None,
+            }),
+            toml::Value::Array(array) => visitor.visit_seq(LenientSeq(array.into_iter())),
+            value => value.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.0.deserialize_enum(name, variants, visitor)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        Self(self.parse()?).deserialize_any(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        Self(self.parse()?).deserialize_any(visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        Self(self.parse()?).deserialize_any(visitor)
+    }
+
+    parse_then_any!(
+        deserialize_seq,
+        deserialize_map,
+        deserialize_bool,
+        deserialize_i8,
+        deserialize_i16,
+        deserialize_i32,
+        deserialize_i64,
+        deserialize_u8,
+        deserialize_u16,
+        deserialize_u32,
+        deserialize_u64,
+        deserialize_f32,
+        deserialize_f64
+    );
+
+    serde::forward_to_deserialize_any! {
+        i128 u128 char str string bytes byte_buf unit unit_struct identifier ignored_any
+    }
+}
+
+struct LenientMap {
+    iter: toml::map::IntoIter,
+    value: Option<toml::Value>,
+}
+
+impl<'de> de::MapAccess<'de> for LenientMap {
+    type Error = toml::de::Error;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        let Some((key, value)) = self.iter.next() else {
+            return Ok(None);
+        };
+
+        self.value = Some(value);
+        seed.deserialize(key.into_deserializer()).map(Some)
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        let value = self
+            .value
+            .take()
+            .expect("Values are only read after their key");
+        seed.deserialize(Lenient(value))
+    }
+}
+
+struct LenientSeq(std::vec::IntoIter<toml::Value>);
+
+impl<'de> de::SeqAccess<'de> for LenientSeq {
+    type Error = toml::de::Error;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        self.0
+            .next()
+            .map(|value| seed.deserialize(Lenient(value)))
+            .transpose()
+    }
+}
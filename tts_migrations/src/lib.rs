@@ -1,17 +1,159 @@
-use std::io::Write;
+use std::{future::Future, pin::Pin};
 
-use sqlx::{Connection as _, Executor, Row};
+use serde::Deserialize as _;
+use sha2::{Digest as _, Sha256};
+use sqlx::{Connection as _, Executor};
+#[cfg(not(feature = "sqlite"))]
+use sqlx::{PgConnection, Row};
 
 use tts_core::{
-    constants::DB_SETUP_QUERY,
-    opt_ext::OptionTryUnwrap,
-    structs::{Config, PostgresConfig, Result, TTSMode},
+    database::{self, DbConnection},
+    structs::{Config, Result, TTSMode},
 };
 
-type Transaction<'a> = sqlx::Transaction<'a, sqlx::Postgres>;
+mod lenient;
 
+use lenient::Lenient;
+
+type MigrationFuture<'a> = Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>>;
+type MigrationFn = for<'a> fn(&'a mut DbConnection, &'a toml::Table) -> MigrationFuture<'a>;
+
+enum Action {
+    Sql(&'static str),
+    Code(MigrationFn),
+}
+
+struct Migration {
+    version: i64,
+    name: &'static str,
+    action: Action,
+}
+
+impl Migration {
+    /// Detects an SQL migration being edited after it was applied, as that edit will never be run.
+    ///
+    /// Code migrations can only be identified by name, so editing one is not detected.
+    fn checksum(&self) -> Vec<u8> {
+        let content = match self.action {
+            Action::Sql(sql) => sql,
+            Action::Code(_) => self.name,
+        };
+
+        Sha256::digest(content).to_vec()
+    }
+}
+
+/// Every migration, in the order they are applied.
+///
+/// Once released, a migration must not be edited, instead add a new one to the end.
+#[cfg(not(feature = "sqlite"))]
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial_schema",
+        action: Action::Sql(include_str!("../migrations/0001_initial_schema.sql")),
+    },
+    Migration {
+        version: 2,
+        name: "unversioned_schema",
+        action: Action::Sql(include_str!("../migrations/0002_unversioned_schema.sql")),
+    },
+    Migration {
+        version: 3,
+        name: "voices_per_mode",
+        action: Action::Code(voices_per_mode),
+    },
+    Migration {
+        version: 4,
+        name: "voice_sessions",
+        action: Action::Sql(include_str!("../migrations/0004_voice_sessions.sql")),
+    },
+    Migration {
+        version: 5,
+        name: "mode_kill_switches",
+        action: Action::Sql(include_str!("../migrations/0005_mode_kill_switches.sql")),
+    },
+    Migration {
+        version: 6,
+        name: "gtts_disabled_kill_switch",
+        action: Action::Code(gtts_disabled_kill_switch),
+    },
+    Migration {
+        version: 7,
+        name: "openai_mode",
+        action: Action::Sql(include_str!("../migrations/0007_openai_mode.sql")),
+    },
+    Migration {
+        version: 8,
+        name: "voice_list_cache",
+        action: Action::Sql(include_str!("../migrations/0008_voice_list_cache.sql")),
+    },
+    Migration {
+        version: 9,
+        name: "fallback_modes",
+        action: Action::Sql(include_str!("../migrations/0009_fallback_modes.sql")),
+    },
+    Migration {
+        version: 10,
+        name: "cache_invalidation",
+        action: Action::Sql(include_str!("../migrations/0010_cache_invalidation.sql")),
+    },
+];
+
+/// Every `SQLite` migration, which started from the Postgres schema at the time it was added.
+#[cfg(feature = "sqlite")]
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial_schema",
+        action: Action::Sql(include_str!("../migrations/sqlite/0001_initial_schema.sql")),
+    },
+    Migration {
+        version: 2,
+        name: "gtts_disabled_kill_switch",
+        action: Action::Code(gtts_disabled_kill_switch),
+    },
+];
+
+#[cfg(not(feature = "sqlite"))]
+const BYTES_TYPE: &str = "bytea";
+#[cfg(feature = "sqlite")]
+const BYTES_TYPE: &str = "blob";
+
+/// How the bot was asked to run, from the command line arguments.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum RunMode {
+    Normal,
+    /// Applies any pending migrations, then exits.
+    MigrateOnly,
+    /// Lists pending migrations and checks they apply, without committing them.
+    DryRun,
+    /// Validates the config and tests each connection, without applying migrations or logging in.
+    CheckConfig,
+}
+
+impl RunMode {
+    pub fn from_args() -> Result<Self> {
+        let mut mode = Self::Normal;
+        for arg in std::env::args().skip(1) {
+            mode = match arg.as_str() {
+                "--migrate-only" if mode != Self::DryRun => Self::MigrateOnly,
+                "--migrate-only" => mode,
+                "--dry-run" => Self::DryRun,
+                "check-config" => Self::CheckConfig,
+                _ => anyhow::bail!(
+                    "Unknown argument `{arg}`, expected `check-config`, `--migrate-only` or `--dry-run`"
+                ),
+            };
+        }
+
+        Ok(mode)
+    }
+}
+
+#[cfg(not(feature = "sqlite"))]
 async fn migrate_single_to_modes(
-    transaction: &mut Transaction<'_>,
+    transaction: &mut PgConnection,
     table: &str,
     new_table: &str,
     old_column: &str,
@@ -66,7 +208,8 @@ async fn migrate_single_to_modes(
     Ok(())
 }
 
-async fn migrate_speaking_rate_to_mode(transaction: &mut Transaction<'_>) -> Result<()> {
+#[cfg(not(feature = "sqlite"))]
+async fn migrate_speaking_rate_to_mode(transaction: &mut PgConnection) -> Result<()> {
     let insert_query = "
         INSERT INTO user_voice(user_id, mode, speaking_rate) VALUES ($1, $2, $3)
         ON CONFLICT (user_id, mode) DO UPDATE SET speaking_rate = EXCLUDED.speaking_rate
@@ -102,129 +245,325 @@ async fn migrate_speaking_rate_to_mode(transaction: &mut Transaction<'_>) -> Res
     Ok(())
 }
 
-// I'll use a proper framework for this one day
-async fn run(config: &mut toml::Table, pool: &sqlx::PgPool) -> Result<()> {
-    let starting_conf = config.clone();
+#[cfg(not(feature = "sqlite"))]
+fn voices_per_mode<'a>(conn: &'a mut PgConnection, _: &'a toml::Table) -> MigrationFuture<'a> {
+    Box::pin(async move {
+        migrate_single_to_modes(conn, "userinfo", "user_voice", "voice", "user_id").await?;
+        migrate_single_to_modes(conn, "guilds", "guild_voice", "default_voice", "guild_id").await?;
+        migrate_speaking_rate_to_mode(conn).await
+    })
+}
+
+/// Replaces the old `gtts_disabled` config key with a kill switch, which can be lifted by command.
+fn gtts_disabled_kill_switch<'a>(
+    conn: &'a mut DbConnection,
+    config: &'a toml::Table,
+) -> MigrationFuture<'a> {
+    Box::pin(async move {
+        let gtts_disabled = config
+            .get("Main")
+            .and_then(|main| main.get("gtts_disabled"))
+            .and_then(|value| bool::deserialize(Lenient(value.clone())).ok())
+            .unwrap_or(false);
+
+        if gtts_disabled {
+            let query = sqlx::query(
+                "INSERT INTO mode_kill_switches(mode, fallback, reason) VALUES ($1, $2, $3) ON CONFLICT (mode) DO NOTHING",
+            );
+
+            conn.execute(
+                query
+                    .bind(TTSMode::gTTS)
+                    .bind(TTSMode::eSpeak)
+                    .bind("Maintenance"),
+            )
+            .await?;
+        }
+
+        Ok(())
+    })
+}
 
-    let stolen_config = std::mem::take(config);
-    *config = pool
+/// Records databases created before migrations were versioned as having the initial schema.
+///
+/// `SQLite` was supported after migrations were versioned, so never needs this.
+#[cfg(not(feature = "sqlite"))]
+async fn baseline(conn: &mut PgConnection) -> Result<()> {
+    let (has_migrations,): (bool,) =
+        sqlx::query_as("SELECT EXISTS(SELECT 1 FROM schema_migrations)")
+            .fetch_one(&mut *conn)
+            .await?;
+
+    let (has_guilds,): (bool,) = sqlx::query_as("SELECT to_regclass('guilds') IS NOT NULL")
+        .fetch_one(&mut *conn)
+        .await?;
+
+    if !has_migrations && has_guilds {
+        let initial = &MIGRATIONS[0];
+        sqlx::query("INSERT INTO schema_migrations(version, name, checksum) VALUES ($1, $2, $3)")
+            .bind(initial.version)
+            .bind(initial.name)
+            .bind(initial.checksum())
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    Ok(())
+}
+
+async fn apply_pending(
+    conn: &mut DbConnection,
+    config: &toml::Table,
+    run_mode: RunMode,
+) -> Result<()> {
+    conn.execute(&*format!(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version     bigint        PRIMARY KEY,
+            name        text          NOT NULL,
+            checksum    {BYTES_TYPE}  NOT NULL,
+            applied_at  timestamp     NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )"
+    ))
+    .await?;
+
+    #[cfg(not(feature = "sqlite"))]
+    baseline(conn).await?;
+
+    let applied: Vec<(i64, Vec<u8>)> =
+        sqlx::query_as("SELECT version, checksum FROM schema_migrations")
+            .fetch_all(&mut *conn)
+            .await?;
+
+    for migration in MIGRATIONS {
+        let checksum = migration.checksum();
+        if let Some((_, applied_checksum)) = applied.iter().find(|(v, _)| *v == migration.version) {
+            if *applied_checksum != checksum {
+                anyhow::bail!(
+                    "Migration {} ({}) has been edited since it was applied",
+                    migration.version,
+                    migration.name
+                );
+            }
+
+            continue;
+        }
+
+        println!(
+            "Applying migration {}: {}",
+            migration.version, migration.name
+        );
+        match migration.action {
+            Action::Sql(sql) => {
+                conn.execute(sql).await?;
+            }
+            Action::Code(func) => func(conn, config).await?,
+        }
+
+        sqlx::query("INSERT INTO schema_migrations(version, name, checksum) VALUES ($1, $2, $3)")
+            .bind(migration.version)
+            .bind(migration.name)
+            .bind(checksum)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    if run_mode == RunMode::DryRun {
+        // Rolls back the transaction, so nothing is actually changed.
+        anyhow::bail!(DryRunComplete);
+    }
+
+    Ok(())
+}
+
+#[derive(Debug)]
+struct DryRunComplete;
+
+impl std::fmt::Display for DryRunComplete {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Dry run complete")
+    }
+}
+
+impl std::error::Error for DryRunComplete {}
+
+/// Applies every pending migration in a single transaction.
+async fn run(config: &toml::Table, pool: &database::Pool, run_mode: RunMode) -> Result<()> {
+    let result = pool
         .acquire()
         .await?
         .transaction(move |transaction| {
-            Box::pin(async move {
-                let mut config = stolen_config;
-                _run(&mut config, transaction).await?;
-                anyhow::Ok(config)
-            })
+            Box::pin(async move { apply_pending(transaction, config, run_mode).await })
         })
-        .await?;
+        .await;
 
-    if &starting_conf != config {
-        let mut config_file = std::fs::File::create("config.toml")?;
-        config_file.write_all(toml::to_string_pretty(&config)?.as_bytes())?;
-    };
-
-    Ok(())
+    match result {
+        Err(err) if err.is::<DryRunComplete>() => {
+            println!("Dry run complete, no migrations have been committed");
+            Ok(())
+        }
+        result => result,
+    }
 }
 
-async fn _run(config: &mut toml::Table, transaction: &mut Transaction<'_>) -> Result<()> {
-    let main_config = config["Main"].as_table_mut().try_unwrap()?;
-    if main_config.get("setup").is_none() {
-        transaction.execute(DB_SETUP_QUERY).await?;
-        main_config.insert("setup".into(), true.into());
+/// Moves config keys which have been renamed, without rewriting the file.
+fn migrate_config(config: &mut toml::Table) {
+    let Some(main_config) = config.get_mut("Main").and_then(toml::Value::as_table_mut) else {
+        return;
+    };
+
+    if main_config.contains_key("gtts_disabled") {
+        println!("`gtts_disabled` has been replaced by the `disable_mode` command, and can be removed from config.toml");
     }
 
     if let Some(patreon_service) = main_config.remove("patreon_service") {
-        let inner = toml::toml!("patreon_service" = patreon_service);
-        config.insert("Premium-Info".into(), toml::Value::Table(inner));
+        println!(
+            "`patreon_service` should be moved to the `[Premium-Info]` section of config.toml"
+        );
+
+        let premium_info = config
+            .entry("Premium-Info")
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+
+        match premium_info.as_table_mut() {
+            Some(premium_info) if !premium_info.contains_key("patreon_service") => {
+                premium_info.insert(String::from("patreon_service"), patreon_service);
+            }
+            _ => println!("`patreon_service` is already set in `[Premium-Info]`, so the value in `[Main]` has been ignored"),
+        }
     }
+}
 
-    transaction.execute("
-        DO $$ BEGIN
-            CREATE type TTSMode AS ENUM (
-                'gtts',
-                'espeak',
-                'premium'
-            );
+/// The config sections which can be set by environment variables without being in the file.
+const CONFIG_SECTIONS: &[&str] = &[
+    "Main",
+    "PostgreSQL-Info",
+    "SQLite-Info",
+    "Webhook-Info",
+    "Logging",
+    "Website-Info",
+    "Premium-Info",
+    "Bot-List-Tokens",
+    "Local-TTS",
+    "OpenAI-TTS",
+    "TTS-Service",
+    "TTS-Mode",
+    "Database-Cache",
+];
+
+/// Reads the config from `TTS_CONFIG_PATH`, or `config.toml` if that is not set.
+///
+/// The default file may be missing, for when the config is only set by environment variables.
+fn read_config() -> Result<toml::Table> {
+    let raw = match std::env::var("TTS_CONFIG_PATH") {
+        Ok(path) => std::fs::read_to_string(path)?,
+        Err(_) => match std::fs::read_to_string("config.toml") {
+            Ok(raw) => raw,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err.into()),
+        },
+    };
 
-            ALTER TYPE TTSMode RENAME VALUE 'premium' TO 'gcloud';
-            ALTER TYPE TTSMode ADD VALUE 'polly';
-        EXCEPTION
-            WHEN OTHERS THEN null;
-        END $$;
+    Ok(raw.parse()?)
+}
 
-        CREATE TABLE IF NOT EXISTS guild_voice (
-            guild_id      bigint,
-            mode          TTSMode,
-            voice         text     NOT NULL,
+/// Compares a config key to part of an environment variable name, such as `PostgreSQL-Info`
+/// to `POSTGRESQL_INFO`.
+fn key_matches(key: &str, segment: &str) -> bool {
+    key.len() == segment.len()
+        && key
+            .chars()
+            .zip(segment.chars())
+            .all(|(k, s)| k.eq_ignore_ascii_case(&s) || (k == '-' && s == '_'))
+}
 
-            PRIMARY KEY (guild_id, mode),
+fn set_env_value(
+    table: &mut toml::Table,
+    known_keys: &[&str],
+    path: &[&str],
+    raw: &str,
+) -> Result<()> {
+    let [segment, rest @ ..] = path else {
+        return Ok(());
+    };
 
-            FOREIGN KEY       (guild_id)
-            REFERENCES guilds (guild_id)
-            ON DELETE CASCADE
-        );
+    let key = table
+        .keys()
+        .map(String::as_str)
+        .chain(known_keys.iter().copied())
+        .find(|key| key_matches(key, segment))
+        .map(ToOwned::to_owned)
+        .unwrap_or_else(|| segment.to_ascii_lowercase());
+
+    let Some(next_segment) = rest.first() else {
+        // The type is decided by the field the value is parsed into, see `Lenient`.
+        table.insert(key, toml::Value::String(raw.to_owned()));
+        return Ok(());
+    };
 
-        CREATE TABLE IF NOT EXISTS user_voice (
-            user_id       bigint,
-            mode          TTSMode,
-            voice         text,
+    // Arrays of tables, such as `[[TTS-Service]]`, are indexed by the next part of the name.
+    let index = next_segment.parse::<usize>().ok();
+    let entry = table.entry(key.clone()).or_insert_with(|| match index {
+        Some(_) => toml::Value::Array(Vec::new()),
+        None => toml::Value::Table(toml::Table::new()),
+    });
+
+    match (entry, index) {
+        (toml::Value::Table(inner), _) => set_env_value(inner, &[], rest, raw),
+        (toml::Value::Array(entries), Some(index)) => {
+            if entries.len() <= index {
+                entries.resize_with(index + 1, || toml::Value::Table(toml::Table::new()));
+            }
 
-            PRIMARY KEY (user_id, mode),
+            let toml::Value::Table(inner) = &mut entries[index] else {
+                anyhow::bail!("`{key}` is not an array of config sections");
+            };
 
-            FOREIGN KEY         (user_id)
-            REFERENCES userinfo (user_id)
-            ON DELETE CASCADE
-        );
+            set_env_value(inner, &[], &rest[1..], raw)
+        }
+        _ => anyhow::bail!(
+            "`{key}` is not a config section, so cannot contain `{}`",
+            rest.join("__")
+        ),
+    }
+}
+
+/// Overrides config values with environment variables, such as `TTS_MAIN__TOKEN`.
+///
+/// Each part of the name after `TTS_` is separated by `__`, and matched ignoring case with
+/// `_` matching `-`, or is an index for arrays of sections such as `TTS_TTS_SERVICE__0__URL`.
+/// Values are parsed as TOML if the field is not a string, such as `TTS_MAIN__TTS_MAX_RETRIES`.
+fn apply_env_overrides(config: &mut toml::Table) -> Result<()> {
+    for (name, value) in std::env::vars_os() {
+        let (Some(name), Some(value)) = (name.to_str(), value.to_str()) else {
+            continue;
+        };
+
+        let Some(path) = name.strip_prefix("TTS_") else {
+            continue;
+        };
+
+        // Skips variables which are not config values, such as `TTS_CONFIG_PATH`.
+        if !path.contains("__") {
+            continue;
+        }
+
+        let path: Vec<&str> = path.split("__").collect();
+        set_env_value(config, CONFIG_SECTIONS, &path, value)?;
+    }
 
-        ALTER TABLE userinfo
-            ADD COLUMN IF NOT EXISTS voice_mode          TTSMode,
-            ADD COLUMN IF NOT EXISTS premium_voice_mode  TTSMode,
-            ADD COLUMN IF NOT EXISTS bot_banned          bool     DEFAULT False,
-            ADD COLUMN IF NOT EXISTS use_new_formatting  bool     DEFAULT False;
-        ALTER TABLE guilds
-            ADD COLUMN IF NOT EXISTS audience_ignore  bool       DEFAULT True,
-            ADD COLUMN IF NOT EXISTS voice_mode       TTSMode    DEFAULT 'gtts',
-            ADD COLUMN IF NOT EXISTS to_translate     bool       DEFAULT False,
-            ADD COLUMN IF NOT EXISTS target_lang      varchar(5),
-            ADD COLUMN IF NOT EXISTS premium_user     bigint,
-            ADD COLUMN IF NOT EXISTS require_voice    bool       DEFAULT True,
-            ADD COLUMN IF NOT EXISTS required_role    bigint,
-            ADD COLUMN IF NOT EXISTS required_prefix  varchar(6),
-            ADD COLUMN IF NOT EXISTS text_in_voice    bool       DEFAULT True,
-            ADD COLUMN IF NOT EXISTS skip_emoji       bool       DEFAULT False;
-        ALTER TABLE user_voice
-            ADD COLUMN IF NOT EXISTS speaking_rate real;
-
-        -- The old table had a pkey on traceback, now we hash and pkey on that
-        ALTER TABLE errors
-            ADD COLUMN IF NOT EXISTS traceback_hash bytea;
-        DELETE FROM errors WHERE traceback_hash IS NULL;
-        ALTER TABLE errors
-            DROP CONSTRAINT IF EXISTS errors_pkey,
-            DROP CONSTRAINT IF EXISTS traceback_hash_pkey,
-            ADD CONSTRAINT traceback_hash_pkey PRIMARY KEY (traceback_hash);
-
-        INSERT INTO user_voice  (user_id, mode)         VALUES(0, 'gtts')       ON CONFLICT (user_id, mode)  DO NOTHING;
-        INSERT INTO guild_voice (guild_id, mode, voice) VALUES(0, 'gtts', 'en') ON CONFLICT (guild_id, mode) DO NOTHING;
-    ").await?;
-
-    migrate_single_to_modes(transaction, "userinfo", "user_voice", "voice", "user_id").await?;
-    migrate_single_to_modes(
-        transaction,
-        "guilds",
-        "guild_voice",
-        "default_voice",
-        "guild_id",
-    )
-    .await?;
-    migrate_speaking_rate_to_mode(transaction).await?;
     Ok(())
 }
 
-pub async fn load_db_and_conf() -> Result<(sqlx::PgPool, Config)> {
-    let mut config_toml: toml::Table = std::fs::read_to_string("config.toml")?.parse()?;
-    let postgres: PostgresConfig = toml::Value::try_into(config_toml["PostgreSQL-Info"].clone())?;
+#[cfg(not(feature = "sqlite"))]
+async fn connect(config_toml: &toml::Table) -> Result<database::Pool> {
+    // The database is picked when building, so point out the feature if SQLite was configured.
+    let Some(postgres) = config_toml.get("PostgreSQL-Info") else {
+        anyhow::bail!(
+            "`[PostgreSQL-Info]` is missing from the config, to use `[SQLite-Info]` instead build with `--features sqlite`"
+        );
+    };
+
+    let postgres = tts_core::structs::PostgresConfig::deserialize(Lenient(postgres.clone()))?;
 
     let pool_config = sqlx::postgres::PgPoolOptions::new();
     let pool_config = if let Some(max_connections) = postgres.max_connections {
@@ -239,9 +578,119 @@ pub async fn load_db_and_conf() -> Result<(sqlx::PgPool, Config)> {
         .database(&postgres.database)
         .password(&postgres.password);
 
-    let pool = pool_config.connect_with(pool_options).await?;
-    run(&mut config_toml, &pool).await?;
+    Ok(pool_config.connect_with(pool_options).await?)
+}
+
+#[cfg(feature = "sqlite")]
+async fn connect(config_toml: &toml::Table) -> Result<database::Pool> {
+    let Some(sqlite) = config_toml.get("SQLite-Info") else {
+        anyhow::bail!(
+            "`[SQLite-Info]` is missing from the config, to use `[PostgreSQL-Info]` instead build without `--features sqlite`"
+        );
+    };
+
+    let sqlite = tts_core::structs::SqliteConfig::deserialize(Lenient(sqlite.clone()))?;
+
+    let pool_options = sqlx::sqlite::SqliteConnectOptions::new()
+        .filename(&sqlite.path)
+        .create_if_missing(true)
+        .foreign_keys(true);
 
-    let config = config_toml.try_into()?;
-    Ok((pool, config))
+    Ok(sqlx::sqlite::SqlitePoolOptions::new()
+        .connect_with(pool_options)
+        .await?)
+}
+
+/// Reads the config file with any environment overrides, without parsing it into [`Config`].
+pub fn load_config() -> Result<toml::Table> {
+    let mut config_toml = read_config()?;
+    migrate_config(&mut config_toml);
+    apply_env_overrides(&mut config_toml)?;
+    Ok(config_toml)
+}
+
+/// Parses the config returned by [`load_config`] into [`Config`].
+pub fn parse_config(config_toml: toml::Table) -> Result<Config> {
+    Ok(Config::deserialize(Lenient(toml::Value::Table(
+        config_toml,
+    )))?)
+}
+
+/// Returns the raw config alongside the parsed one, for finding what a reload has changed.
+pub async fn load_db_and_conf(run_mode: RunMode) -> Result<(database::Pool, Config, toml::Table)> {
+    let config_toml = load_config()?;
+    let pool = connect(&config_toml).await?;
+    if run_mode == RunMode::CheckConfig {
+        println!("Connected to the database");
+    } else {
+        run(&config_toml, &pool, run_mode).await?;
+    }
+
+    let config = parse_config(config_toml.clone())?;
+    Ok((pool, config, config_toml))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(serde::Deserialize)]
+    struct TestConfig {
+        #[serde(rename = "Main")]
+        main: TestMain,
+        #[serde(rename = "TTS-Service", default)]
+        tts_services: Vec<TestService>,
+    }
+
+    #[derive(serde::Deserialize)]
+    struct TestMain {
+        password: String,
+        channel: String,
+        max_retries: u32,
+        modes: Vec<String>,
+    }
+
+    #[derive(serde::Deserialize)]
+    struct TestService {
+        url: String,
+        weight: u32,
+    }
+
+    fn set(table: &mut toml::Table, name: &str, raw: &str) {
+        let path: Vec<&str> = name.split("__").collect();
+        set_env_value(table, &["Main", "TTS-Service"], &path, raw).unwrap();
+    }
+
+    #[test]
+    fn env_values_take_the_type_of_their_field() {
+        let mut table: toml::Table = "[Main]\nmax_retries = 2".parse().unwrap();
+        set(&mut table, "MAIN__PASSWORD", "1234");
+        set(&mut table, "MAIN__CHANNEL", "[x]");
+        set(&mut table, "MAIN__MAX_RETRIES", "5");
+        set(&mut table, "MAIN__MODES", r#"["gtts", "espeak"]"#);
+
+        let config = TestConfig::deserialize(Lenient(toml::Value::Table(table))).unwrap();
+        assert_eq!(config.main.password, "1234");
+        assert_eq!(config.main.channel, "[x]");
+        assert_eq!(config.main.max_retries, 5);
+        assert_eq!(config.main.modes, ["gtts", "espeak"]);
+    }
+
+    #[test]
+    fn env_values_index_arrays_of_sections() {
+        let raw = "[Main]\npassword = 'a'\nchannel = 'b'\nmax_retries = 2\nmodes = []\n\n[[TTS-Service]]\nurl = 'http://a'\nweight = 1";
+        let mut table: toml::Table = raw.parse().unwrap();
+        set(&mut table, "TTS_SERVICE__0__WEIGHT", "3");
+        set(&mut table, "TTS_SERVICE__1__URL", "http://b");
+        set(&mut table, "TTS_SERVICE__1__WEIGHT", "1");
+
+        let config = TestConfig::deserialize(Lenient(toml::Value::Table(table))).unwrap();
+        let services: Vec<_> = config
+            .tts_services
+            .iter()
+            .map(|service| (service.url.as_str(), service.weight))
+            .collect();
+
+        assert_eq!(services, [("http://a", 3), ("http://b", 1)]);
+    }
 }
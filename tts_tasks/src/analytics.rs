@@ -20,7 +20,7 @@ impl crate::Looper for Arc<analytics::Handler> {
                         "
                     INSERT INTO analytics(event, is_command, count)
                     VALUES($1, $2, $3)
-                    ON CONFLICT ON CONSTRAINT analytics_pkey
+                    ON CONFLICT (event, is_command, date_collected)
                     DO UPDATE SET count = analytics.count + EXCLUDED.count
                 ;",
                     );
@@ -6,12 +6,14 @@ use serde_json::{json, to_vec};
 use self::serenity::UserId;
 use serenity::all as serenity;
 
-use tts_core::structs::{BotListTokens, Result};
+use tts_core::structs::{BotListTokens, Data, Result};
 
+/// Sends the server count to each bot list, if `[Bot-List-Tokens]` is set.
+///
+/// The tokens are read each time, so they can be changed by a config reload.
 pub struct BotListUpdater {
     cache: Arc<serenity::cache::Cache>,
-    reqwest: reqwest::Client,
-    tokens: BotListTokens,
+    data: Arc<Data>,
 }
 
 struct BotListReq {
@@ -22,27 +24,19 @@ struct BotListReq {
 
 impl BotListUpdater {
     #[must_use]
-    pub fn new(
-        reqwest: reqwest::Client,
-        cache: Arc<serenity::cache::Cache>,
-        tokens: BotListTokens,
-    ) -> Self {
-        Self {
-            cache,
-            reqwest,
-            tokens,
-        }
+    pub fn new(cache: Arc<serenity::cache::Cache>, data: Arc<Data>) -> Self {
+        Self { cache, data }
     }
 
     fn top_gg_data(
-        &self,
+        tokens: &BotListTokens,
         bot_id: UserId,
         guild_count: usize,
         shard_count: NonZeroU16,
     ) -> BotListReq {
         BotListReq {
             url: format!("https://top.gg/api/bots/{bot_id}/stats"),
-            token: HeaderValue::from_str(self.tokens.top_gg.as_str()).unwrap(),
+            token: HeaderValue::from_str(tokens.top_gg.as_str()).unwrap(),
             body: to_vec(&json!({
                 "server_count": guild_count,
                 "shard_count": shard_count,
@@ -52,14 +46,14 @@ impl BotListUpdater {
     }
 
     fn discord_bots_gg_data(
-        &self,
+        tokens: &BotListTokens,
         bot_id: UserId,
         guild_count: usize,
         shard_count: NonZeroU16,
     ) -> BotListReq {
         BotListReq {
             url: format!("https://discord.bots.gg/api/v1/bots/{bot_id}/stats"),
-            token: HeaderValue::from_str(self.tokens.discord_bots_gg.as_str()).unwrap(),
+            token: HeaderValue::from_str(tokens.discord_bots_gg.as_str()).unwrap(),
             body: to_vec(&json!({
                 "guildCount": guild_count,
                 "shardCount": shard_count,
@@ -68,10 +62,14 @@ impl BotListUpdater {
         }
     }
 
-    fn bots_on_discord_data(&self, bot_id: UserId, guild_count: usize) -> BotListReq {
+    fn bots_on_discord_data(
+        tokens: &BotListTokens,
+        bot_id: UserId,
+        guild_count: usize,
+    ) -> BotListReq {
         BotListReq {
             url: format!("https://bots.ondiscord.xyz/bot-api/bots/{bot_id}/guilds"),
-            token: HeaderValue::from_str(self.tokens.bots_on_discord.as_str()).unwrap(),
+            token: HeaderValue::from_str(tokens.bots_on_discord.as_str()).unwrap(),
             body: to_vec(&json!({"guildCount": guild_count})).unwrap(),
         }
     }
@@ -82,13 +80,17 @@ impl crate::Looper for BotListUpdater {
     const MILLIS: u64 = 1000 * 60 * 60;
 
     async fn loop_func(&self) -> Result<()> {
+        let Some(tokens) = self.data.bot_list_tokens.lock().clone() else {
+            return Ok(());
+        };
+
         let perform = |BotListReq { url, body, token }| async move {
             let headers = reqwest::header::HeaderMap::from_iter([
                 (AUTHORIZATION, token),
                 (CONTENT_TYPE, HeaderValue::from_static("application/json")),
             ]);
 
-            let request = self.reqwest.post(url).body(body).headers(headers);
+            let request = self.data.reqwest.post(url).body(body).headers(headers);
 
             let resp_res = request.send().await;
             if let Err(err) = resp_res.and_then(reqwest::Response::error_for_status) {
@@ -101,9 +103,14 @@ impl crate::Looper for BotListUpdater {
         let guild_count = self.cache.guild_count();
 
         tokio::join!(
-            perform(self.bots_on_discord_data(bot_id, guild_count)),
-            perform(self.top_gg_data(bot_id, guild_count, shard_count)),
-            perform(self.discord_bots_gg_data(bot_id, guild_count, shard_count)),
+            perform(Self::bots_on_discord_data(&tokens, bot_id, guild_count)),
+            perform(Self::top_gg_data(&tokens, bot_id, guild_count, shard_count)),
+            perform(Self::discord_bots_gg_data(
+                &tokens,
+                bot_id,
+                guild_count,
+                shard_count
+            )),
         );
 
         Ok(())
@@ -0,0 +1,143 @@
+use std::{sync::Arc, time::Duration};
+
+use sqlx::postgres::PgListener;
+
+use tts_core::{
+    structs::{Data, TTSMode},
+    tasks::TASKS,
+};
+
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum Key {
+    Id(i64),
+    Mode(String),
+}
+
+/// The payload sent by the `notify_cache_invalidation` trigger.
+#[derive(serde::Deserialize)]
+struct Invalidation {
+    table: String,
+    keys: (Option<Key>, Option<Key>),
+}
+
+fn invalidate(data: &Data, invalidation: Invalidation) -> anyhow::Result<()> {
+    let (first, second) = invalidation.keys;
+    let Some(Key::Id(first)) = first else {
+        anyhow::bail!("Invalid key for {}", invalidation.table);
+    };
+
+    match (invalidation.table.as_str(), second) {
+        ("guilds", None) => data.guilds_db.invalidate_cache(&first),
+        ("userinfo", None) => data.userinfo_db.invalidate_cache(&first),
+        ("nicknames", Some(Key::Id(second))) => data.nickname_db.invalidate_cache(&[first, second]),
+        ("user_voice", Some(Key::Mode(mode))) => {
+            let mode: TTSMode = mode.parse()?;
+            data.user_voice_db.invalidate_cache(&(first, mode));
+        }
+        ("guild_voice", Some(Key::Mode(mode))) => {
+            let mode: TTSMode = mode.parse()?;
+            data.guild_voice_db.invalidate_cache(&(first, mode));
+        }
+        (table, _) => anyhow::bail!("Invalid invalidation for table {table}"),
+    }
+
+    Ok(())
+}
+
+/// Drops cached rows changed by other bot processes, such as other clusters or the dashboard.
+///
+/// Unlike a [`Looper`](crate::Looper), this keeps listening until shutdown, so each connection
+/// attempt is tracked as a run instead, to report a listener which cannot connect.
+pub struct CacheInvalidator(pub Arc<Data>);
+
+impl CacheInvalidator {
+    const NAME: &'static str = "Cache Invalidator";
+    /// How long to wait between connection attempts.
+    const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+    pub async fn start(self) {
+        tracing::info!("{}: Started background task", Self::NAME);
+        TASKS.register(Self::NAME, Self::RETRY_DELAY);
+
+        loop {
+            let Some(run_guard) = TASKS.start_run(Self::NAME) else {
+                break;
+            };
+
+            let started = tokio::time::Instant::now();
+            let listener = self.connect().await;
+            crate::record_run(Self::NAME, &listener);
+            drop(run_guard);
+
+            if let Ok(listener) = listener {
+                tokio::select! {
+                    err = self.listen(listener) => {
+                        tracing::warn!("{}: Lost the listener: {err:?}", Self::NAME);
+                    }
+                    () = TASKS.cancelled() => break,
+                }
+            }
+
+            let next_run = started + TASKS.schedule_next(Self::NAME, Self::RETRY_DELAY);
+            if tokio::time::timeout_at(next_run, TASKS.cancelled())
+                .await
+                .is_ok()
+            {
+                break;
+            }
+        }
+
+        TASKS.stop(Self::NAME);
+        tracing::info!("{}: Stopped background task", Self::NAME);
+    }
+
+    async fn connect(&self) -> anyhow::Result<PgListener> {
+        let mut listener = PgListener::connect_with(&self.0.pool).await?;
+        listener.listen("cache_invalidation").await?;
+
+        // Changes may have been missed while not listening.
+        self.invalidate_all();
+        Ok(listener)
+    }
+
+    /// Only returns if the listener fails, to be reconnected by [`Self::start`].
+    async fn listen(&self, mut listener: PgListener) -> anyhow::Error {
+        loop {
+            let notification = match listener.try_recv().await {
+                Ok(Some(notification)) => notification,
+                Ok(None) => {
+                    // The connection was lost, and will be re-established on the next call.
+                    self.invalidate_all();
+                    continue;
+                }
+                Err(err) => return err.into(),
+            };
+
+            let payload = notification.payload();
+            let invalidation = match serde_json::from_str(payload) {
+                Ok(invalidation) => invalidation,
+                Err(err) => {
+                    tracing::warn!(
+                        "{}: Skipping malformed payload `{payload}`: {err}",
+                        Self::NAME
+                    );
+                    continue;
+                }
+            };
+
+            if let Err(err) = invalidate(&self.0, invalidation) {
+                tracing::warn!("{}: {err:?}", Self::NAME);
+            }
+        }
+    }
+
+    fn invalidate_all(&self) {
+        let data = &self.0;
+        data.guilds_db.invalidate_all();
+        data.userinfo_db.invalidate_all();
+        data.nickname_db.invalidate_all();
+        data.user_voice_db.invalidate_all();
+        data.guild_voice_db.invalidate_all();
+    }
+}
@@ -2,9 +2,52 @@
 
 mod analytics;
 pub mod bot_list_updater;
+#[cfg(not(feature = "sqlite"))]
+pub mod cache_invalidator;
 pub mod logging;
+mod tts_health;
+pub mod voice_refresher;
 pub mod web_updater;
 
+use std::time::Duration;
+
+use tts_core::{
+    metrics::METRICS,
+    tasks::{ALERT_AFTER_FAILURES, TASKS},
+};
+
+/// Records the outcome of a task's run, alerting if enough runs in a row have failed.
+fn record_run<T>(name: &'static str, result: &anyhow::Result<T>) {
+    let labels = [name];
+    METRICS.task_runs.with_label_values(&labels).inc();
+    match result {
+        Ok(_) => {
+            let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH);
+            let now = now.map(|now| now.as_secs() as i64).unwrap_or_default();
+            METRICS
+                .task_last_success
+                .with_label_values(&labels)
+                .set(now);
+
+            let previous_failures = TASKS.finish_run(name, None);
+            if previous_failures >= ALERT_AFTER_FAILURES {
+                tracing::info!("{name}: Recovered after {previous_failures} failed runs");
+            }
+        }
+        Err(err) => {
+            METRICS.task_failures.with_label_values(&labels).inc();
+
+            // Single failures are usually transient, so only a streak is sent as an error.
+            let failures = TASKS.finish_run(name, Some(format!("{err:?}"))) + 1;
+            if failures == ALERT_AFTER_FAILURES {
+                tracing::error!("{name}: Failed {failures} runs in a row: {err:?}");
+            } else {
+                tracing::warn!("{name} Error: {err:?}");
+            }
+        }
+    }
+}
+
 pub trait Looper {
     const NAME: &'static str;
     const MILLIS: u64;
@@ -15,12 +58,30 @@ pub trait Looper {
         Self: Sized,
     {
         tracing::info!("{}: Started background task", Self::NAME);
-        let mut interval = tokio::time::interval(std::time::Duration::from_millis(Self::MILLIS));
+        let period = Duration::from_millis(Self::MILLIS);
+        TASKS.register(Self::NAME, period);
+
         loop {
-            interval.tick().await;
-            if let Err(err) = self.loop_func().await {
-                tracing::error!("{} Error: {:?}", Self::NAME, err);
+            let Some(run_guard) = TASKS.start_run(Self::NAME) else {
+                break;
+            };
+
+            let started = tokio::time::Instant::now();
+            record_run(Self::NAME, &self.loop_func().await);
+
+            drop(run_guard);
+
+            // Runs keep to the period, instead of waiting a full period after each run.
+            let next_run = started + TASKS.schedule_next(Self::NAME, period);
+            if tokio::time::timeout_at(next_run, TASKS.cancelled())
+                .await
+                .is_ok()
+            {
+                break;
             }
         }
+
+        TASKS.stop(Self::NAME);
+        tracing::info!("{}: Stopped background task", Self::NAME);
     }
 }
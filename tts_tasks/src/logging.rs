@@ -1,16 +1,43 @@
-use std::{collections::HashMap, fmt::Write, sync::Arc};
+use std::{collections::HashMap, fmt::Write, io::Write as _, sync::Arc, time::Duration};
 
 use aformat::{aformat, CapStr};
 use anyhow::Result;
 use itertools::Itertools as _;
-use parking_lot::Mutex;
+use opentelemetry::{trace::TracerProvider as _, KeyValue};
+use opentelemetry_otlp::WithExportConfig as _;
+use parking_lot::{Mutex, RwLock};
+use tracing::{
+    field::{Field, Visit},
+    span, Subscriber,
+};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::{
+    layer::{Context, SubscriberExt as _},
+    registry::{LookupSpan, Scope},
+    Layer,
+};
 
 use serenity::all::{ExecuteWebhook, Http, Webhook};
 
+use tts_core::structs::{LogFormat, LoggingConfig};
+
 use crate::Looper;
 
 type LogMessage = (&'static str, String);
 
+/// The crates of the bot itself, which are logged at `log_level` unless overridden.
+const BOT_CRATES: [&str; 6] = [
+    "discord_tts_bot",
+    "tts_core",
+    "tts_commands",
+    "tts_events",
+    "tts_tasks",
+    "tts_migrations",
+];
+
+/// How many daily log files are kept, if `max_log_files` is not set.
+const DEFAULT_MAX_LOG_FILES: usize = 14;
+
 fn get_avatar(level: tracing::Level) -> &'static str {
     match level {
         tracing::Level::TRACE | tracing::Level::DEBUG => {
@@ -22,27 +49,283 @@ fn get_avatar(level: tracing::Level) -> &'static str {
     }
 }
 
+fn is_within(target: &str, prefix: &str) -> bool {
+    target
+        .strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+}
+
+/// The most verbose level logged for each target.
+pub struct LogFilter {
+    default: tracing::Level,
+    /// Sorted longest first, so the most specific target is matched.
+    targets: Vec<(String, tracing::Level)>,
+}
+
+impl LogFilter {
+    /// Targets not in `targets` are logged at `default` for the bot's crates, or at most `WARN`.
+    #[must_use]
+    pub fn new(default: tracing::Level, mut targets: Vec<(String, tracing::Level)>) -> Self {
+        targets.sort_by_key(|(target, _)| std::cmp::Reverse(target.len()));
+        Self { default, targets }
+    }
+
+    fn level_for(&self, target: &str) -> tracing::Level {
+        let configured = self
+            .targets
+            .iter()
+            .find(|(prefix, _)| is_within(target, prefix));
+
+        if let Some((_, level)) = configured {
+            *level
+        } else if BOT_CRATES.iter().any(|name| is_within(target, name)) {
+            self.default
+        } else {
+            self.default.min(tracing::Level::WARN)
+        }
+    }
+}
+
+/// Collects the `message` field and the other fields of an event or span.
+#[derive(Default)]
+struct FieldVisitor {
+    message: String,
+    fields: Vec<(&'static str, String)>,
+}
+
+impl Visit for FieldVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            write!(self.message, "{value:?}").unwrap();
+        } else {
+            self.fields.push((field.name(), format!("{value:?}")));
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            self.fields.push((field.name(), value.to_owned()));
+        }
+    }
+}
+
+/// Sets up exporting spans over OTLP/gRPC, such as to an OpenTelemetry collector or Jaeger.
+fn init_otlp(endpoint: &str) -> Result<opentelemetry_sdk::trace::Tracer> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()?;
+
+    let resource = opentelemetry_sdk::Resource::new([KeyValue::new("service.name", "tts-bot")]);
+    let provider = opentelemetry_sdk::trace::TracerProvider::builder()
+        .with_batch_exporter(exporter, opentelemetry_sdk::runtime::Tokio)
+        .with_resource(resource)
+        .build();
+
+    let tracer = provider.tracer(env!("CARGO_CRATE_NAME"));
+    opentelemetry::global::set_tracer_provider(provider);
+    Ok(tracer)
+}
+
+/// The fields of a span, kept in its extensions to be shown with each event inside it.
+struct SpanFields(Vec<(&'static str, String)>);
+
+/// Formats the spans from the root inwards, such as `outer{id=1}:inner`.
+fn span_context<S>(scope: Option<Scope<'_, S>>) -> String
+where
+    S: for<'a> LookupSpan<'a>,
+{
+    let Some(scope) = scope else {
+        return String::new();
+    };
+
+    scope
+        .from_root()
+        .map(|span| {
+            let mut formatted = String::from(span.name());
+            if let Some(SpanFields(fields)) = span.extensions().get::<SpanFields>() {
+                if !fields.is_empty() {
+                    let fields = fields
+                        .iter()
+                        .map(|(name, value)| format!("{name}={value}"))
+                        .join(" ");
+
+                    write!(formatted, "{{{fields}}}").unwrap();
+                }
+            }
+
+            formatted
+        })
+        .join(":")
+}
+
+struct LogRecord {
+    level: tracing::Level,
+    target: &'static str,
+    /// The spans the event happened in, such as `outer{id=1}:inner`.
+    spans: String,
+    message: String,
+    fields: Vec<(&'static str, String)>,
+}
+
+impl LogRecord {
+    /// The message with its spans and fields, without the level or target.
+    fn body(&self) -> String {
+        let mut body = String::new();
+        if !self.spans.is_empty() {
+            write!(body, "{}: ", self.spans).unwrap();
+        }
+
+        body.push_str(&self.message);
+        for (name, value) in &self.fields {
+            write!(body, " {name}={value}").unwrap();
+        }
+
+        body
+    }
+
+    fn format(&self, format: LogFormat) -> String {
+        let timestamp = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+        match format {
+            LogFormat::Text => format!(
+                "{timestamp} {:>5} {}: {}",
+                self.level.as_str(),
+                self.target,
+                self.body()
+            ),
+            LogFormat::Json => {
+                let fields: serde_json::Map<_, _> = self
+                    .fields
+                    .iter()
+                    .map(|(name, value)| ((*name).to_owned(), value.clone().into()))
+                    .collect();
+
+                serde_json::json!({
+                    "timestamp": timestamp,
+                    "level": self.level.as_str(),
+                    "target": self.target,
+                    "spans": self.spans,
+                    "message": self.message,
+                    "fields": fields,
+                })
+                .to_string()
+            }
+        }
+    }
+}
+
+/// Sends logs to the log webhooks, and to stdout or rotating log files if configured.
 pub struct WebhookLogger {
     http: Arc<Http>,
 
     pending_logs: Mutex<HashMap<tracing::Level, Vec<LogMessage>>>,
+    filter: RwLock<LogFilter>,
 
-    normal_logs: Webhook,
-    error_logs: Webhook,
+    stdout: bool,
+    file: Option<Mutex<RollingFileAppender>>,
+    format: LogFormat,
+    otlp_enabled: bool,
+
+    normal_logs: RwLock<Option<Webhook>>,
+    error_logs: RwLock<Option<Webhook>>,
 }
 
 impl WebhookLogger {
-    pub fn init(http: Arc<Http>, normal_logs: Webhook, error_logs: Webhook) {
+    pub fn init(
+        http: Arc<Http>,
+        filter: LogFilter,
+        config: &LoggingConfig,
+        normal_logs: Option<Webhook>,
+        error_logs: Option<Webhook>,
+    ) -> Result<Arc<Self>> {
+        let file = if let Some(directory) = &config.directory {
+            let appender = RollingFileAppender::builder()
+                .rotation(Rotation::DAILY)
+                .max_log_files(config.max_log_files.unwrap_or(DEFAULT_MAX_LOG_FILES))
+                .filename_prefix("tts_bot")
+                .filename_suffix("log")
+                .build(directory.as_str())?;
+
+            Some(Mutex::new(appender))
+        } else {
+            None
+        };
+
+        let otel_tracer = config.otlp_endpoint.as_deref().map(init_otlp).transpose()?;
+
         let logger = ArcWrapper(Arc::new(Self {
             http,
-            normal_logs,
-            error_logs,
+            filter: RwLock::new(filter),
+
+            // Without a log webhook, stdout is the only place logs would go by default.
+            stdout: config.stdout.unwrap_or(normal_logs.is_none()),
+            file,
+            format: config.format,
+            otlp_enabled: otel_tracer.is_some(),
+
+            normal_logs: RwLock::new(normal_logs),
+            error_logs: RwLock::new(error_logs),
 
             pending_logs: Mutex::default(),
         }));
 
-        tracing::subscriber::set_global_default(logger.clone()).unwrap();
-        tokio::spawn(logger.0.start());
+        let subscriber = tracing_subscriber::registry()
+            .with(logger.clone())
+            .with(otel_tracer.map(|tracer| tracing_opentelemetry::layer().with_tracer(tracer)));
+
+        tracing::subscriber::set_global_default(subscriber)?;
+        tokio::spawn(logger.0.clone().start());
+        Ok(logger.0)
+    }
+
+    /// Changes which logs are recorded, for a config reload.
+    pub fn set_filter(&self, filter: LogFilter) {
+        *self.filter.write() = filter;
+
+        // `enabled` is cached for each log statement, so must be asked again.
+        tracing::callsite::rebuild_interest_cache();
+    }
+
+    /// Changes where logs are sent, for a config reload.
+    pub fn set_webhooks(&self, normal_logs: Option<Webhook>, error_logs: Option<Webhook>) {
+        *self.normal_logs.write() = normal_logs;
+        *self.error_logs.write() = error_logs;
+    }
+
+    /// Sends any spans which have not been exported yet, for shutdown.
+    pub async fn shutdown_tracing(&self) {
+        if !self.otlp_enabled {
+            return;
+        }
+
+        // Shutting down the provider blocks until the exporter is finished, or gives up.
+        let shutdown = tokio::task::spawn_blocking(opentelemetry::global::shutdown_tracer_provider);
+        if tokio::time::timeout(Duration::from_secs(5), shutdown)
+            .await
+            .is_err()
+        {
+            tracing::warn!("Timed out exporting the remaining spans");
+        }
+    }
+
+    fn write_local(&self, record: &LogRecord) {
+        if !self.stdout && self.file.is_none() {
+            return;
+        }
+
+        let line = record.format(self.format);
+        if self.stdout {
+            // Nothing can be done if stdout is closed, and the error cannot be logged.
+            let _ = writeln!(std::io::stdout().lock(), "{line}");
+        }
+
+        if let Some(file) = &self.file {
+            if let Err(err) = writeln!(file.lock(), "{line}") {
+                eprintln!("Failed to write to log file: {err}");
+            }
+        }
     }
 }
 
@@ -54,6 +337,17 @@ impl Looper for Arc<WebhookLogger> {
         let pending_logs = self.pending_logs.lock().drain().collect::<HashMap<_, _>>();
 
         for (severity, messages) in pending_logs {
+            let webhook = if tracing::Level::ERROR >= severity {
+                self.error_logs.read().clone()
+            } else {
+                self.normal_logs.read().clone()
+            };
+
+            // The webhook was removed by a config reload since these were logged.
+            let Some(webhook) = webhook else {
+                continue;
+            };
+
             let mut chunks: Vec<String> = Vec::with_capacity(messages.len());
             let mut pre_chunked = String::new();
 
@@ -82,12 +376,6 @@ impl Looper for Arc<WebhookLogger> {
                 }
             }
 
-            let webhook = if tracing::Level::ERROR >= severity {
-                &self.error_logs
-            } else {
-                &self.normal_logs
-            };
-
             let webhook_name = aformat!("TTS-Webhook [{}]", CapStr::<5>(severity.as_str()));
 
             for chunk in chunks {
@@ -104,60 +392,69 @@ impl Looper for Arc<WebhookLogger> {
     }
 }
 
-impl tracing::Subscriber for ArcWrapper<WebhookLogger> {
-    // Hopefully this works
-    fn new_span(&self, _span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
-        tracing::span::Id::from_u64(1)
-    }
-
-    fn record_follows_from(&self, _span: &tracing::span::Id, _follows: &tracing::span::Id) {}
-    fn record(&self, _span: &tracing::span::Id, _values: &tracing::span::Record<'_>) {}
-    fn enter(&self, _span: &tracing::span::Id) {}
-    fn exit(&self, _span: &tracing::span::Id) {}
+impl<S> Layer<S> for ArcWrapper<WebhookLogger>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attributes: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let mut visitor = FieldVisitor::default();
+        attributes.record(&mut visitor);
 
-    fn event(&self, event: &tracing::Event<'_>) {
-        pub struct StringVisitor<'a> {
-            string: &'a mut String,
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanFields(visitor.fields));
         }
+    }
 
-        impl tracing::field::Visit for StringVisitor<'_> {
-            fn record_debug(
-                &mut self,
-                _field: &tracing::field::Field,
-                value: &dyn std::fmt::Debug,
-            ) {
-                write!(self.string, "{value:?}").unwrap();
-            }
+    fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+        let mut visitor = FieldVisitor::default();
+        values.record(&mut visitor);
 
-            fn record_str(&mut self, _field: &tracing::field::Field, value: &str) {
-                self.string.push_str(value);
-            }
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+
+        let mut extensions = span.extensions_mut();
+        if let Some(SpanFields(fields)) = extensions.get_mut::<SpanFields>() {
+            fields.extend(visitor.fields);
         }
+    }
 
-        let mut message = String::new();
-        event.record(&mut StringVisitor {
-            string: &mut message,
-        });
+    fn on_event(&self, event: &tracing::Event<'_>, ctx: Context<'_, S>) {
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
 
         let metadata = event.metadata();
-        self.pending_logs
-            .lock()
-            .entry(*metadata.level())
-            .or_default()
-            .push((metadata.target(), message));
-    }
+        let record = LogRecord {
+            level: *metadata.level(),
+            target: metadata.target(),
+            spans: span_context(ctx.event_scope(event)),
+            message: visitor.message,
+            fields: visitor.fields,
+        };
+
+        self.write_local(&record);
 
-    fn enabled(&self, metadata: &tracing::Metadata<'_>) -> bool {
-        let target = metadata.target();
-        if target.starts_with(env!("CARGO_CRATE_NAME")) {
-            tracing::Level::INFO >= *metadata.level()
+        let has_webhook = if tracing::Level::ERROR >= record.level {
+            self.error_logs.read().is_some()
         } else {
-            tracing::Level::WARN >= *metadata.level()
+            self.normal_logs.read().is_some()
+        };
+
+        if has_webhook {
+            self.pending_logs
+                .lock()
+                .entry(record.level)
+                .or_default()
+                .push((record.target, record.body()));
         }
     }
+
+    fn enabled(&self, metadata: &tracing::Metadata<'_>, _ctx: Context<'_, S>) -> bool {
+        self.filter.read().level_for(metadata.target()) >= *metadata.level()
+    }
 }
 
-// So we can impl tracing::Subscriber for Arc<WebhookLogger>
+// So we can impl tracing_subscriber::Layer for Arc<WebhookLogger>
 pub struct ArcWrapper<T>(pub Arc<T>);
 impl<T> Clone for ArcWrapper<T> {
     fn clone(&self) -> Self {
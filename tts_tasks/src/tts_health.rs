@@ -0,0 +1,13 @@
+use std::sync::Arc;
+
+use tts_core::backends::Endpoints;
+
+impl crate::Looper for Arc<Endpoints> {
+    const NAME: &'static str = "TTS Health";
+    const MILLIS: u64 = 1000 * 15;
+
+    async fn loop_func(&self) -> anyhow::Result<()> {
+        self.probe().await;
+        Ok(())
+    }
+}
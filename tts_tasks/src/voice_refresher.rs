@@ -0,0 +1,16 @@
+use std::sync::Arc;
+
+use tts_core::{structs::Data, voice_lists};
+
+/// Re-fetches the voice lists, so new voices can be used without a restart.
+pub struct VoiceListRefresher(pub Arc<Data>);
+
+impl crate::Looper for VoiceListRefresher {
+    const NAME: &'static str = "Voice List Refresher";
+    const MILLIS: u64 = 1000 * 60 * 60 * 6;
+
+    async fn loop_func(&self) -> anyhow::Result<()> {
+        voice_lists::refresh(&self.0).await?;
+        Ok(())
+    }
+}
@@ -7,17 +7,10 @@ use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
 
 use serenity::all as serenity;
 
-use tts_core::structs::{Result, TTSMode, WebsiteInfo};
-
-#[allow(dead_code, clippy::match_same_arms)]
-fn remember_to_update_analytics_query() {
-    match TTSMode::gTTS {
-        TTSMode::gTTS => (),
-        TTSMode::Polly => (),
-        TTSMode::eSpeak => (),
-        TTSMode::gCloud => (),
-    }
-}
+use tts_core::{
+    database,
+    structs::{Data, Result},
+};
 
 fn count_members<'a>(guilds: impl Iterator<Item = serenity::cache::GuildRef<'a>>) -> u64 {
     guilds.map(|g| g.member_count).sum()
@@ -32,12 +25,12 @@ struct Statistics {
     user_count: u64,
 }
 
+/// Sends statistics to the website, if `[Website-Info]` is set.
+///
+/// The config is read each time, so it can be changed by a config reload.
 pub struct Updater {
-    pub patreon_service: Option<reqwest::Url>,
+    pub data: Arc<Data>,
     pub cache: Arc<serenity::Cache>,
-    pub reqwest: reqwest::Client,
-    pub config: WebsiteInfo,
-    pub pool: sqlx::PgPool,
 }
 
 impl crate::Looper for Updater {
@@ -55,9 +48,18 @@ impl crate::Looper for Updater {
             guild_id: i64,
         }
 
-        let patreon_members = if let Some(mut patreon_service) = self.patreon_service.clone() {
+        let Some(config) = self.data.website_info.lock().clone() else {
+            return Ok(());
+        };
+
+        let patreon_service = self
+            .data
+            .premium_config()
+            .map(|c| c.patreon_service.clone());
+        let patreon_members = if let Some(mut patreon_service) = patreon_service {
             patreon_service.set_path("members");
             let raw_members: HashMap<i64, serde::de::IgnoredAny> = self
+                .data
                 .reqwest
                 .get(patreon_service)
                 .send()
@@ -72,33 +74,43 @@ impl crate::Looper for Updater {
         };
 
         let (message_count, premium_guild_ids) = {
-            let mut db_conn = self.pool.acquire().await?;
-            let message_count = sqlx::query_as::<_, AnalyticsQueryResult>(
+            let mut db_conn = self.data.pool.acquire().await?;
+            let message_count_query = format!(
                 "
                 SELECT count FROM analytics
-                WHERE date_collected = (CURRENT_DATE - 1) AND (
-                    event = 'gTTS_tts'   OR
-                    event = 'eSpeak_tts' OR
-                    event = 'gCloud_tts' OR
-                    event = 'Polly_tts'
-                )
+                WHERE date_collected = ({}) AND event LIKE '%\\_tts' ESCAPE '\\'
             ",
-            )
-            .fetch_all(&mut *db_conn)
-            .await?
-            .into_iter()
-            .map(|r| r.count as i64)
-            .sum::<i64>();
-
-            let premium_guild_ids = sqlx::query_as::<_, PremiumGuildsQueryResult>(
-                "SELECT guild_id FROM guilds WHERE premium_user = ANY($1)",
-            )
-            .bind(&patreon_members)
-            .fetch_all(&mut *db_conn)
-            .await?
-            .into_iter()
-            .map(|g| g.guild_id)
-            .collect::<HashSet<_>>();
+                database::YESTERDAY
+            );
+
+            let message_count = sqlx::query_as::<_, AnalyticsQueryResult>(&message_count_query)
+                .fetch_all(&mut *db_conn)
+                .await?
+                .into_iter()
+                .map(|r| r.count as i64)
+                .sum::<i64>();
+
+            let premium_guild_ids = if patreon_members.is_empty() {
+                HashSet::new()
+            } else {
+                let premium_guilds_query = format!(
+                    "SELECT guild_id FROM guilds WHERE premium_user IN ({})",
+                    database::placeholders(1, patreon_members.len())
+                );
+
+                let mut query =
+                    sqlx::query_as::<_, PremiumGuildsQueryResult>(&premium_guilds_query);
+                for &member in &patreon_members {
+                    query = query.bind(member);
+                }
+
+                query
+                    .fetch_all(&mut *db_conn)
+                    .await?
+                    .into_iter()
+                    .map(|g| g.guild_id)
+                    .collect()
+            };
 
             (message_count, premium_guild_ids)
         };
@@ -122,14 +134,15 @@ impl crate::Looper for Updater {
         };
 
         let url = {
-            let mut url = self.config.url.clone();
+            let mut url = config.url.clone();
             url.set_path("/update_stats");
             url
         };
 
-        self.reqwest
+        self.data
+            .reqwest
             .post(url)
-            .header(AUTHORIZATION, self.config.stats_key.clone())
+            .header(AUTHORIZATION, config.stats_key)
             .header(CONTENT_TYPE, "application/json")
             .body(serde_json::to_string(&stats)?)
             .send()